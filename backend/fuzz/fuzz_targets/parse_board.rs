@@ -0,0 +1,12 @@
+//! Malformed .brd input must result in errors, never in a panic:
+//!   cargo +nightly fuzz run parse_board
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use backend::roborally::state::parse_board;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(content) = std::str::from_utf8(data) {
+        let _ = parse_board(String::from(content));
+    }
+});
\ No newline at end of file
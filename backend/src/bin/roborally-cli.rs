@@ -0,0 +1,181 @@
+//! Plays a game in the terminal, without server or browser:
+//!   roborally-cli [--deck <deck name>] [--priorities random|official] [<board name> [<seat>...]]
+//! Seats are one of: human, random, greedy, avoid-death. Defaults to test-full-1 with a human and a greedy bot, playing
+//! the standard deck with random priorities. Decks are read from data/decks and list their own priorities.
+use failure::{ Error, format_err };
+
+use std::env;
+use std::io::{ self, BufRead, Write };
+use std::path::Path;
+
+use backend::roborally::state::{ BoardCatalogue, BoardConfig, EBotLevel, EGamePhase, EGameResult, EPriorityScheme, ERoundPhase, ESeat, GameConfig, GameState, MoveCard, MoveCardID, PlayerConfig, ProgramCardDeckConfig, StartPositionID, DEFAULT_BOARD, DEFAULT_BOARDS_DIRECTORY, deck_path, render_state };
+use backend::roborally::engine::game_engine::GameEngine;
+use backend::roborally::engine::player_input::{ ProgramInput, StartPositionInput };
+
+fn main() -> Result<(), Error> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (board_name, seats, deck_config) = parse_args(&args)?;
+
+    let catalogue = BoardCatalogue::load_from_directory(Path::new(DEFAULT_BOARDS_DIRECTORY))?;
+    let config = GameConfig {
+        deck_config,
+        board_config: BoardConfig::new(catalogue.get_board_or_fail(&board_name)?),
+        player_config: PlayerConfig::with_seats(seats),
+    };
+    let mut game_state = GameState::create_from(&config)?;
+    let engine = GameEngine::new();
+    engine.initialize(&mut game_state)?;
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut shown_rounds = 0;
+    loop {
+        // Show the rounds that have been played since the last input
+        let played_rounds: Vec<String> = game_state.all_rounds()
+            .skip(shown_rounds)
+            .filter(|r| r.phase != ERoundPhase::PROGRAMMING)
+            .map(|r| format!("=== Round {} played:\n{}", r.id, render_state(&r.state)))
+            .collect();
+        shown_rounds += played_rounds.len();
+        for round in played_rounds {
+            println!("{}", round);
+        }
+
+        match &game_state.game_result {
+            EGameResult::None => (),
+            EGameResult::Win{ player_id } => {
+                println!("Player {} wins!", player_id);
+                return Ok(());
+            },
+            EGameResult::Draw{ player_ids } => {
+                println!("Draw between players {:?}", player_ids);
+                return Ok(());
+            },
+        }
+
+        let result = match game_state.phase {
+            EGamePhase::PREPARATION => choose_start_position(&engine, &mut game_state, &mut lines),
+            EGamePhase::RUNNING => choose_program(&engine, &mut game_state, &mut lines),
+            _ => return Ok(()),
+        };
+        match result {
+            Ok(true) => (),
+            Ok(false) => return Ok(()),     // End of input
+            Err(err) => println!("Error: {}", err),
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<(String, Vec<ESeat>, ProgramCardDeckConfig), Error> {
+    let mut deck_config = ProgramCardDeckConfig::default();
+    let mut positional = vec![];
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        let mut value = || it.next().ok_or_else(|| format_err!("Missing value for '{}'", arg));
+        match arg.as_str() {
+            "--deck" => deck_config.deck_file = Some(deck_path(value()?)?),
+            "--priorities" => deck_config.priority_scheme = match value()?.as_str() {
+                "random" => EPriorityScheme::Random,
+                "official" => EPriorityScheme::Official,
+                scheme => return Err(format_err!("Unknown priorities '{}', expected one of: random, official", scheme)),
+            },
+            _ => positional.push(arg),
+        }
+    }
+
+    let board_name = positional.first().map(|b| b.to_string()).unwrap_or_else(|| String::from(DEFAULT_BOARD));
+    if positional.len() < 2 {
+        return Ok((board_name, vec![ESeat::Human, ESeat::Bot{ level: EBotLevel::Greedy }], deck_config));
+    }
+
+    let seats = positional[1..].iter()
+        .map(|seat| match seat.as_str() {
+            "human" => Ok(ESeat::Human),
+            "random" => Ok(ESeat::Bot{ level: EBotLevel::Random }),
+            "greedy" => Ok(ESeat::Bot{ level: EBotLevel::Greedy }),
+            "avoid-death" => Ok(ESeat::Bot{ level: EBotLevel::AvoidDeath }),
+            _ => Err(format_err!("Unknown seat '{}', expected one of: human, random, greedy, avoid-death", seat)),
+        })
+        .collect::<Result<Vec<ESeat>, Error>>()?;
+    Ok((board_name, seats, deck_config))
+}
+
+type Lines<'a> = io::Lines<io::StdinLock<'a>>;
+
+/// Returns false if the input has ended
+fn choose_start_position(engine: &GameEngine, game_state: &mut GameState, lines: &mut Lines) -> Result<bool, Error> {
+    let state = &game_state.start_state;
+    let player_id = state.all_players()
+        .find(|p| p.input_required)
+        .map(|p| p.id)
+        .ok_or_else(|| format_err!("No player left to choose a start position"))?;
+    let mut free_start_positions: Vec<StartPositionID> = state.board.tiles.iter()
+        .filter(|t| state.get_robot_at_position(&t.position).is_none())
+        .filter_map(|t| t.start_position_id)
+        .collect();
+    free_start_positions.sort();
+
+    println!("{}", render_state(state));
+    let line = match prompt(lines, &format!("Player {}: choose a start position {:?}> ", player_id, free_start_positions))? {
+        Some(line) => line,
+        None => return Ok(false),
+    };
+    let start_position_id: StartPositionID = line.trim().parse()?;
+    engine.set_start_position(game_state, &StartPositionInput{ player_id, start_position_id })?;
+    Ok(true)
+}
+
+/// Returns false if the input has ended
+fn choose_program(engine: &GameEngine, game_state: &mut GameState, lines: &mut Lines) -> Result<bool, Error> {
+    let state = game_state.current_round()?.state.clone();
+    let player = match state.active_players().find(|p| p.bot_level().is_none() && !p.has_programmed_registers()) {
+        Some(player) => player,
+        None => {
+            // Bots only: they play one round at a time
+            if prompt(lines, "Press enter to play the next round> ")?.is_none() {
+                return Ok(false);
+            }
+            engine.run_bots(game_state)?;
+            return Ok(true);
+        },
+    };
+    let register_count = player.count_unlocked_registers();
+
+    println!("{}", render_state(&state));
+    println!("Player {}, your cards:", player.id);
+    for (i, card) in player.program_card_deck.iter().enumerate() {
+        println!("  [{}] {}", i, describe_card(card));
+    }
+    let line = match prompt(lines, &format!("Player {}: choose {} cards by number> ", player.id, register_count))? {
+        Some(line) => line,
+        None => return Ok(false),
+    };
+    let register_cards_choices = line.split_whitespace()
+        .map(|index| {
+            let index: usize = index.parse()?;
+            player.program_card_deck.get(index)
+                .map(|c| c.id)
+                .ok_or_else(|| format_err!("No card [{}]", index))
+        })
+        .collect::<Result<Vec<MoveCardID>, Error>>()?;
+
+    let input = ProgramInput{ player_id: player.id, register_cards_choices };
+    engine.set_player_program_input(game_state, &input)?;
+    Ok(true)
+}
+
+fn prompt(lines: &mut Lines, text: &str) -> Result<Option<String>, Error> {
+    print!("{}", text);
+    io::stdout().flush()?;
+    match lines.next() {
+        None => Ok(None),
+        Some(line) => Ok(Some(line?)),
+    }
+}
+
+fn describe_card(card: &MoveCard) -> String {
+    let moves: Vec<String> = card.tmove.iter()
+        .map(|m| format!("{:?}", m))
+        .collect();
+    format!("{} (priority {})", moves.join(" + "), card.priority)
+}
\ No newline at end of file
@@ -0,0 +1,257 @@
+//! The chat of a game: players send text messages and emotes, everyone (spectators included) may read them. Only a
+//! limited number of messages is kept. Texts pass a MessageFilter before they are posted, which may reject or rewrite
+//! them.
+//!
+//! Each human seat gets a secret token when a game starts (see Chat::start_game). Messages are only posted for a
+//! player if they carry the token of that player's seat.
+use failure::Fail;
+use rand::Rng;
+
+use std::collections::{ BTreeMap, VecDeque };
+
+use crate::roborally::state::{ ESeat, GameState, PlayerID };
+
+pub const DEFAULT_HISTORY_SIZE: usize = 100;
+pub const MAX_MESSAGE_LENGTH: usize = 280;
+
+#[derive(Debug, Fail)]
+pub enum ChatError {
+    #[fail(display = "Player {} is not part of this game", player_id)]
+    UnknownSender {
+        player_id: PlayerID,
+    },
+    #[fail(display = "Player {} is a bot and cannot chat", player_id)]
+    BotSender {
+        player_id: PlayerID,
+    },
+    #[fail(display = "Wrong seat token for player {}", player_id)]
+    InvalidSeatToken {
+        player_id: PlayerID,
+    },
+    #[fail(display = "Empty chat message")]
+    EmptyMessage,
+    #[fail(display = "Chat message too long: {} characters, at most {} allowed", length, max_length)]
+    MessageTooLong {
+        length: usize,
+        max_length: usize,
+    },
+    #[fail(display = "Chat message rejected: {}", reason)]
+    MessageRejected {
+        reason: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EEmote {
+    ThumbsUp,
+    Laugh,
+    Facepalm,
+    Boom,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EChatContent {
+    Text(String),
+    Emote(EEmote),
+}
+
+pub type ChatMessageID = u64;
+
+/// Proves that a client sits at a seat. Only the client that started the game gets them.
+pub type SeatToken = String;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatMessage {
+    pub id: ChatMessageID,
+    pub player_id: PlayerID,
+    pub content: EChatContent,
+}
+
+/// Checks texts before they are posted. Returns the text to post (which may differ, e.g. masked) or why it is rejected.
+pub trait MessageFilter: Send + Sync {
+    fn check(&self, text: &str) -> Result<String, ChatError>;
+}
+
+/// Rejects empty and overly long texts and masks banned words (case-insensitive) with '*'
+#[derive(Debug, Clone)]
+pub struct DefaultMessageFilter {
+    pub max_length: usize,
+    pub banned_words: Vec<String>,
+}
+
+impl Default for DefaultMessageFilter {
+    fn default() -> Self {
+        DefaultMessageFilter {
+            max_length: MAX_MESSAGE_LENGTH,
+            banned_words: vec![],
+        }
+    }
+}
+
+impl MessageFilter for DefaultMessageFilter {
+    fn check(&self, text: &str) -> Result<String, ChatError> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(ChatError::EmptyMessage);
+        }
+        let length = text.chars().count();
+        if length > self.max_length {
+            return Err(ChatError::MessageTooLong{ length, max_length: self.max_length });
+        }
+
+        let words: Vec<String> = text.split(' ')
+            .map(|word| {
+                let bare = word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase();
+                if !bare.is_empty() && self.banned_words.iter().any(|b| b.to_lowercase() == bare) {
+                    word.chars().map(|c| if c.is_alphanumeric() { '*' } else { c }).collect()
+                } else {
+                    String::from(word)
+                }
+            })
+            .collect();
+        Ok(words.join(" "))
+    }
+}
+
+pub struct Chat {
+    history: VecDeque<ChatMessage>,
+    history_size: usize,
+    next_id: ChatMessageID,
+    filter: Box<dyn MessageFilter>,
+    seat_tokens: BTreeMap<PlayerID, SeatToken>,
+}
+
+impl Default for Chat {
+    fn default() -> Self {
+        Chat::new(DEFAULT_HISTORY_SIZE, Box::new(DefaultMessageFilter::default()))
+    }
+}
+
+impl Chat {
+    pub fn new(history_size: usize, filter: Box<dyn MessageFilter>) -> Chat {
+        Chat {
+            history: VecDeque::with_capacity(history_size),
+            history_size,
+            next_id: 0,
+            filter,
+            seat_tokens: BTreeMap::new(),
+        }
+    }
+
+    /// Forgets all messages and tokens of the previous game and issues a new token for each human seat of game_state
+    pub fn start_game(&mut self, game_state: &GameState) -> &BTreeMap<PlayerID, SeatToken> {
+        self.history.clear();
+        let mut rng = rand::thread_rng();
+        self.seat_tokens = game_state.start_state.all_players()
+            .filter(|p| p.seat == ESeat::Human)
+            .map(|p| (p.id, format!("{:032x}", rng.gen::<u128>())))
+            .collect();
+        &self.seat_tokens
+    }
+
+    /// Posts a message by a player of game_state. Only humans sitting at the table may chat, with the token of their
+    /// seat.
+    pub fn post(&mut self, game_state: &GameState, player_id: PlayerID, seat_token: &str, content: EChatContent) -> Result<&ChatMessage, ChatError> {
+        let player = game_state.start_state.get_player_or_fail(player_id)
+            .map_err(|_| ChatError::UnknownSender{ player_id })?;
+        if player.seat != ESeat::Human {
+            return Err(ChatError::BotSender{ player_id });
+        }
+        if self.seat_tokens.get(&player_id).map_or(true, |token| token != seat_token) {
+            return Err(ChatError::InvalidSeatToken{ player_id });
+        }
+        let content = match content {
+            EChatContent::Text(text) => EChatContent::Text(self.filter.check(&text)?),
+            emote => emote,
+        };
+
+        if self.history.len() == self.history_size {
+            self.history.pop_front();
+        }
+        self.history.push_back(ChatMessage {
+            id: self.next_id,
+            player_id,
+            content,
+        });
+        self.next_id += 1;
+        Ok(self.history.back().unwrap())
+    }
+
+    /// The last messages, oldest first
+    pub fn history(&self) -> impl Iterator<Item=&ChatMessage> {
+        self.history.iter()
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use crate::roborally::state::{ EBotLevel, ESeat, GameConfig, GameState, PlayerConfig };
+    use super::*;
+
+    fn create_game_state() -> GameState {
+        let config = GameConfig {
+            player_config: PlayerConfig::with_seats(vec![ESeat::Human, ESeat::Bot{ level: EBotLevel::Random }]),
+            ..GameConfig::default()
+        };
+        GameState::create_from(&config).unwrap()
+    }
+
+    fn text(text: &str) -> EChatContent {
+        EChatContent::Text(String::from(text))
+    }
+
+    #[test]
+    fn test_history_size() -> Result<(), ChatError> {
+        let game_state = create_game_state();
+        let mut chat = Chat::new(3, Box::new(DefaultMessageFilter::default()));
+        let token = chat.start_game(&game_state)[&0].clone();
+        for i in 0..5 {
+            chat.post(&game_state, 0, &token, text(&format!("message {}", i)))?;
+        }
+        chat.post(&game_state, 0, &token, EChatContent::Emote(EEmote::Boom))?;
+
+        let history: Vec<(ChatMessageID, EChatContent)> = chat.history().map(|m| (m.id, m.content.clone())).collect();
+        assert_eq!(history, vec![(3, text("message 3")), (4, text("message 4")), (5, EChatContent::Emote(EEmote::Boom))]);
+
+        chat.start_game(&game_state);
+        assert_eq!(chat.history().count(), 0, "cleared");
+        Ok(())
+    }
+
+    #[test]
+    fn test_senders() {
+        let game_state = create_game_state();
+        let mut chat = Chat::default();
+        let tokens = chat.start_game(&game_state).clone();
+        assert_eq!(tokens.keys().collect::<Vec<&PlayerID>>(), vec![&0], "tokens for humans only");
+        let token = &tokens[&0];
+        assert!(chat.post(&game_state, 0, token, text("hi")).is_ok(), "human");
+        assert!(matches!(chat.post(&game_state, 0, "guessed", text("hi")), Err(ChatError::InvalidSeatToken{ player_id: 0 })), "wrong token");
+        assert!(matches!(chat.post(&game_state, 1, token, text("beep")), Err(ChatError::BotSender{ player_id: 1 })), "bot");
+        assert!(matches!(chat.post(&game_state, 2, token, text("hi")), Err(ChatError::UnknownSender{ player_id: 2 })), "not seated");
+
+        chat.start_game(&game_state);
+        assert!(matches!(chat.post(&game_state, 0, token, text("hi")), Err(ChatError::InvalidSeatToken{ player_id: 0 })), "token of the previous game");
+    }
+
+    #[test]
+    fn test_default_message_filter() {
+        let filter = DefaultMessageFilter {
+            max_length: 20,
+            banned_words: vec![String::from("darn")],
+        };
+        let cases = [
+            ("  gg  ", Ok("gg")),
+            ("Darn, that pit!", Ok("****, that pit!")),
+            ("darnit", Ok("darnit")),
+            ("", Err("Empty chat message")),
+            ("   ", Err("Empty chat message")),
+            ("this message is too long", Err("Chat message too long: 24 characters, at most 20 allowed")),
+        ];
+        for (input, expected) in &cases {
+            let actual = filter.check(input).map_err(|e| format!("{}", e));
+            assert_eq!(actual.as_ref().map(|s| s.as_str()).map_err(|e| e.as_str()), *expected, "{:?}", input);
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,5 @@
+pub mod protocol;
+pub mod service;
+pub mod chat;
+pub mod status;
+pub mod roborally;
\ No newline at end of file
@@ -1,28 +1,31 @@
-mod protocol;
-mod service;
-mod roborally;
-
 use tonic::transport::Server;
+use failure::Fail;
 
 use std::env;
+use std::path::PathBuf;
 
-use protocol::robo_rally_game_server::RoboRallyGameServer;
+use backend::protocol::robo_rally_game_server::RoboRallyGameServer;
 
-use service::RoboRallyGameService;
+use backend::service::RoboRallyGameService;
+use backend::roborally::state::{ BoardCatalogue, DEFAULT_BOARDS_DIRECTORY };
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args: Vec<String> = env::args().collect();
-    let socket_addr = match &args.as_slice()[1..] {
+    let (socket_addr, boards_directory) = match &args.as_slice()[1..] {
         [socket_addr_str] => {
-            socket_addr_str.parse().unwrap()
+            (socket_addr_str.parse().unwrap(), PathBuf::from(DEFAULT_BOARDS_DIRECTORY))
+        },
+        [socket_addr_str, boards_directory_str] => {
+            (socket_addr_str.parse().unwrap(), PathBuf::from(boards_directory_str))
         },
         _ => {
-            panic!("Expected arguments: <address>:<port>!")
+            panic!("Expected arguments: <address>:<port> [<boards directory>]!")
         }
     };
 
-    let service = RoboRallyGameService::default();
+    let catalogue = BoardCatalogue::load_from_directory(&boards_directory).map_err(|e| e.compat())?;
+    let service = RoboRallyGameService::new(catalogue);
     Server::builder()
         .add_service(RoboRallyGameServer::new(service))
         .serve(socket_addr)
@@ -1,8 +1,10 @@
 use failure::Fail;
 
+use crate::chat;
 use crate::roborally::state;
 use crate::roborally::engine::player_input;
 use crate::roborally::engine::register_engine;
+use crate::roborally::engine::program_simulation;
 
 tonic::include_proto!("protocol");
 
@@ -12,6 +14,25 @@ pub enum ProtocolError {
     #[fail(display = "Missing player input!")]
     MissingPlayerInput {
     },
+    #[fail(display = "Invalid seat: {}", seat)]
+    InvalidSeat {
+        seat: i32,
+    },
+    #[fail(display = "Invalid priority scheme: {}", scheme)]
+    InvalidPriorityScheme {
+        scheme: i32,
+    },
+    #[fail(display = "Invalid simulation mode: {}", mode)]
+    InvalidSimulationMode {
+        mode: i32,
+    },
+    #[fail(display = "Missing chat content!")]
+    MissingChatContent {
+    },
+    #[fail(display = "Invalid emote: {}", emote)]
+    InvalidEmote {
+        emote: i32,
+    },
 }
 
 impl player_input::ProgramInput {
@@ -36,6 +57,58 @@ impl player_input::StartPositionInput {
     }
 }
 
+impl state::ESeat {
+    pub fn parse_from(seat: i32) -> Result<state::ESeat, ProtocolError> {
+        let seat = match ESeat::from_i32(seat) {
+            None => return Err(ProtocolError::InvalidSeat{ seat }),
+            Some(ESeat::Human) => state::ESeat::Human,
+            Some(ESeat::BotRandom) => state::ESeat::Bot{ level: state::EBotLevel::Random },
+            Some(ESeat::BotGreedy) => state::ESeat::Bot{ level: state::EBotLevel::Greedy },
+            Some(ESeat::BotAvoidDeath) => state::ESeat::Bot{ level: state::EBotLevel::AvoidDeath },
+        };
+        Ok(seat)
+    }
+}
+
+impl state::EPriorityScheme {
+    pub fn parse_from(scheme: i32) -> Result<state::EPriorityScheme, ProtocolError> {
+        match EPriorityScheme::from_i32(scheme) {
+            None => Err(ProtocolError::InvalidPriorityScheme{ scheme }),
+            Some(EPriorityScheme::Random) => Ok(state::EPriorityScheme::Random),
+            Some(EPriorityScheme::Official) => Ok(state::EPriorityScheme::Official),
+        }
+    }
+}
+
+impl register_engine::ESimulationMode {
+    pub fn parse_from(mode: i32) -> Result<register_engine::ESimulationMode, ProtocolError> {
+        match ESimulationMode::from_i32(mode) {
+            None => Err(ProtocolError::InvalidSimulationMode{ mode }),
+            Some(ESimulationMode::Static) => Ok(register_engine::ESimulationMode::Static),
+            Some(ESimulationMode::Idle) => Ok(register_engine::ESimulationMode::Idle),
+        }
+    }
+}
+
+impl chat::EChatContent {
+    pub fn parse_from(content: Option<ChatContent>) -> Result<chat::EChatContent, ProtocolError> {
+        let content = content.and_then(|c| c.content).ok_or(ProtocolError::MissingChatContent{})?;
+        match content {
+            chat_content::Content::Text(text) => Ok(chat::EChatContent::Text(text)),
+            chat_content::Content::Emote(emote) => {
+                let emote = match EEmote::from_i32(emote) {
+                    None => return Err(ProtocolError::InvalidEmote{ emote }),
+                    Some(EEmote::ThumbsUp) => chat::EEmote::ThumbsUp,
+                    Some(EEmote::Laugh) => chat::EEmote::Laugh,
+                    Some(EEmote::Facepalm) => chat::EEmote::Facepalm,
+                    Some(EEmote::Boom) => chat::EEmote::Boom,
+                };
+                Ok(chat::EChatContent::Emote(emote))
+            },
+        }
+    }
+}
+
 impl From<ESimpleMove> for register_engine::ESimpleMove {
     fn from(mmove: ESimpleMove) -> register_engine::ESimpleMove {
         match mmove {
@@ -61,6 +134,7 @@ impl From<&state::GameState> for GameState {
             start_state: Some(State::from(game_state.start_state.borrow())),
             rounds: game_state.all_rounds().map(Round::from).collect(),
             game_result: from_game_result(&game_state.game_result),
+            spectator_count: 0,
         }
     }
 }
@@ -91,6 +165,69 @@ impl From<&state::Round> for Round {
             id: round.id,
             phase: ERoundPhase::from(round.phase).into(),
             state: Some(State::from(round.state.borrow())),
+            programmed_state: round.programmed_state.as_ref().map(|s| State::from(s.borrow())),
+        }
+    }
+}
+
+impl From<&chat::ChatMessage> for ChatMessage {
+    fn from(message: &chat::ChatMessage) -> ChatMessage {
+        let content = match &message.content {
+            chat::EChatContent::Text(text) => chat_content::Content::Text(text.clone()),
+            chat::EChatContent::Emote(emote) => chat_content::Content::Emote(EEmote::from(*emote).into()),
+        };
+        ChatMessage {
+            id: message.id,
+            player_id: message.player_id,
+            content: Some(ChatContent{ content: Some(content) }),
+        }
+    }
+}
+
+impl From<chat::EEmote> for EEmote {
+    fn from(emote: chat::EEmote) -> EEmote {
+        match emote {
+            chat::EEmote::ThumbsUp => EEmote::ThumbsUp,
+            chat::EEmote::Laugh => EEmote::Laugh,
+            chat::EEmote::Facepalm => EEmote::Facepalm,
+            chat::EEmote::Boom => EEmote::Boom,
+        }
+    }
+}
+
+// Redaction: what spectators may see
+impl GameState {
+    /// Hides what only the players know: the deck, the hands and the registers programmed in a round that is still
+    /// being programmed. Locked registers stay visible, their cards have been played before. With
+    /// reveal_hands_after_round, the hands of executed rounds are shown (in their programmed_state).
+    pub fn redact_for_spectators(&mut self, reveal_hands_after_round: bool) {
+        for state in self.initial_state.iter_mut().chain(self.start_state.iter_mut()) {
+            state.redact(true, true);
+        }
+        for round in &mut self.rounds {
+            let programming = round.phase == ERoundPhase::Initialization as i32 || round.phase == ERoundPhase::Programming as i32;
+            if let Some(state) = &mut round.state {
+                state.redact(true, programming);
+            }
+            if let Some(state) = &mut round.programmed_state {
+                state.redact(!reveal_hands_after_round, false);
+            }
+        }
+    }
+}
+
+impl State {
+    fn redact(&mut self, hide_hands: bool, hide_registers: bool) {
+        self.cards.clear();
+        for player in &mut self.players {
+            if hide_hands {
+                player.program_card_deck.clear();
+            }
+            if hide_registers {
+                for register in player.registers.iter_mut().filter(|r| !r.locked) {
+                    register.move_card = None;
+                }
+            }
         }
     }
 }
@@ -139,6 +276,18 @@ impl From<&state::Player> for Player {
             registers,
             program_card_deck,
             input_required: player.input_required,
+            seat: ESeat::from(player.seat).into(),
+        }
+    }
+}
+
+impl From<state::ESeat> for ESeat {
+    fn from(seat: state::ESeat) -> ESeat {
+        match seat {
+            state::ESeat::Human => ESeat::Human,
+            state::ESeat::Bot{ level: state::EBotLevel::Random } => ESeat::BotRandom,
+            state::ESeat::Bot{ level: state::EBotLevel::Greedy } => ESeat::BotGreedy,
+            state::ESeat::Bot{ level: state::EBotLevel::AvoidDeath } => ESeat::BotAvoidDeath,
         }
     }
 }
@@ -148,6 +297,7 @@ impl From<&state::Register> for Register {
         Register {
             move_card: register.move_card.as_ref().map(MoveCard::from),
             locked: register.locked,
+            carried_over: register.is_carried_over(),
         }
     }
 }
@@ -163,6 +313,25 @@ impl From<&state::MoveCard> for MoveCard {
             id: card.id,
             priority: card.priority,
             moves,
+            kind: ECardKind::from(&card.kind).into(),
+            name: format!("{}", card.kind),
+        }
+    }
+}
+
+impl From<&state::ECardKind> for ECardKind {
+    fn from(kind: &state::ECardKind) -> ECardKind {
+        match kind {
+            state::ECardKind::Move1 => ECardKind::Move1,
+            state::ECardKind::Move2 => ECardKind::Move2,
+            state::ECardKind::Move3 => ECardKind::Move3,
+            state::ECardKind::BackUp => ECardKind::BackUp,
+            state::ECardKind::TurnLeft => ECardKind::TurnLeft,
+            state::ECardKind::TurnRight => ECardKind::TurnRight,
+            state::ECardKind::UTurn => ECardKind::UTurn,
+            state::ECardKind::StepLeft => ECardKind::StepLeft,
+            state::ECardKind::StepRight => ECardKind::StepRight,
+            state::ECardKind::Custom{ .. } => ECardKind::Custom,
         }
     }
 }
@@ -189,6 +358,7 @@ impl From<&state::Robot> for Robot {
             direction: EDirection::from(robot.direction).into(),
             damage: robot.damage,
             life_tokens: robot.life_tokens,
+            is_virtual: robot.is_virtual,
         }
     }
 }
@@ -245,6 +415,9 @@ impl From<state::ETileType> for ETileType {
             state::ETileType::Conveyor2{ .. } => Conveyor2,
             state::ETileType::Conveyor3{ .. } => Conveyor3,
             state::ETileType::Rotator{ .. } => Rotator,
+            state::ETileType::Crusher{ .. } => Crusher,
+            state::ETileType::Portal{ .. } => Portal,
+            state::ETileType::Slippery => Slippery,
         }
     }
 }
@@ -254,17 +427,22 @@ impl From<state::ETileType> for TileType {
         let fields: Option<tile_type::Fields> = match ttype {
             state::ETileType::Regular => None,
             state::ETileType::NoTile => None,
+            state::ETileType::Slippery => None,
             state::ETileType::Conveyor2{ input, out, express } => Some(tile_type::Fields::Conveyor2(Conveyor2 {
                 input: input as i32,
                 out: out as i32,
                 express,
             })),
             state::ETileType::Conveyor3{ inputs, out, express } => Some(tile_type::Fields::Conveyor3(Conveyor3 {
-                inputs: inputs.iter().map(|i| *i as i32).collect(),
+                inputs: inputs.as_slice().iter().map(|i| *i as i32).collect(),
                 out: out as i32,
                 express,
             })),
             state::ETileType::Rotator{ dir } => Some(tile_type::Fields::Rotator(Rotator { dir: dir as i32 })),
+            state::ETileType::Crusher{ registers } => Some(tile_type::Fields::Crusher(Crusher {
+                registers: registers.indices().into_iter().map(|i| i as u32).collect(),
+            })),
+            state::ETileType::Portal{ label } => Some(tile_type::Fields::Portal(Portal { label: label.to_string() })),
         };
         let typ: ETileType = ttype.into();
         TileType{
@@ -290,4 +468,163 @@ impl From<&state::Position> for Position {
             y: pos.y,
         }
     }
+}
+
+impl From<&state::BoardCatalogueEntry> for BoardInfo {
+    fn from(entry: &state::BoardCatalogueEntry) -> BoardInfo {
+        let errors = entry.errors.iter()
+            .map(BoardError::from)
+            .collect();
+        let (size_x, size_y, start_position_count) = match &entry.board {
+            None => (0, 0, 0),
+            Some(board) => (board.size_x, board.size_y, board.start_position_count() as u32),
+        };
+        BoardInfo {
+            name: entry.name.clone(),
+            size_x,
+            size_y,
+            start_position_count,
+            valid: entry.is_valid(),
+            errors,
+            issues: entry.issues.iter().map(BoardIssue::from).collect(),
+        }
+    }
+}
+
+impl From<&state::ParserError> for BoardError {
+    fn from(err: &state::ParserError) -> BoardError {
+        BoardError {
+            message: format!("{}", err),
+            location: err.location().map(BoardErrorLocation::from),
+            glyph: err.glyph().map(String::from).unwrap_or_default(),
+        }
+    }
+}
+
+impl From<&state::SourceLocation> for BoardErrorLocation {
+    fn from(location: &state::SourceLocation) -> BoardErrorLocation {
+        BoardErrorLocation {
+            line: location.line as u32,
+            column: location.column as u32,
+            row_kind: ERowKind::from(location.row_kind).into(),
+        }
+    }
+}
+
+impl From<state::ERowKind> for ERowKind {
+    fn from(row_kind: state::ERowKind) -> ERowKind {
+        match row_kind {
+            state::ERowKind::Wall => ERowKind::WallRow,
+            state::ERowKind::Tile => ERowKind::TileRow,
+        }
+    }
+}
+
+impl From<&state::BoardIssue> for BoardIssue {
+    fn from(issue: &state::BoardIssue) -> BoardIssue {
+        BoardIssue {
+            severity: EIssueSeverity::from(issue.severity).into(),
+            message: format!("{}", issue),
+            position: issue.position.as_ref().map(Position::from),
+        }
+    }
+}
+
+impl From<state::EIssueSeverity> for EIssueSeverity {
+    fn from(severity: state::EIssueSeverity) -> EIssueSeverity {
+        match severity {
+            state::EIssueSeverity::Warning => EIssueSeverity::Warning,
+            state::EIssueSeverity::Error => EIssueSeverity::Error,
+        }
+    }
+}
+
+impl From<&program_simulation::SimulatedRegister> for SimulatedRegister {
+    fn from(register: &program_simulation::SimulatedRegister) -> SimulatedRegister {
+        SimulatedRegister {
+            register_index: register.register_index as u32,
+            position: Some((&register.position).into()),
+            direction: EDirection::from(register.direction).into(),
+            destroyed: register.destroyed,
+            events: register.events.iter()
+                .map(SimulationEvent::from)
+                .collect(),
+        }
+    }
+}
+
+impl From<&program_simulation::ESimulationEvent> for SimulationEvent {
+    fn from(event: &program_simulation::ESimulationEvent) -> SimulationEvent {
+        let event = match event {
+            program_simulation::ESimulationEvent::RobotPushed{ player_id, from, to } => simulation_event::Event::Pushed(RobotPushed {
+                player_id: *player_id,
+                from: Some(from.into()),
+                to: Some(to.into()),
+            }),
+            program_simulation::ESimulationEvent::RobotDied{ player_id, position } => simulation_event::Event::Died(RobotDied {
+                player_id: *player_id,
+                position: Some(position.into()),
+            }),
+        };
+        SimulationEvent {
+            event: Some(event),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use failure::Error;
+
+    use crate::roborally::state as s;
+    use crate::roborally::engine::game_engine::GameEngine;
+    use super::{ ERoundPhase, GameState };
+
+    /// Bots choose their start positions and play the first round
+    fn played_game() -> Result<s::GameState, Error> {
+        let config = s::GameConfig {
+            player_config: s::PlayerConfig::with_seats(vec![
+                s::ESeat::Bot{ level: s::EBotLevel::AvoidDeath },
+                s::ESeat::Bot{ level: s::EBotLevel::AvoidDeath },
+            ]),
+            ..s::GameConfig::default()
+        };
+        let mut game_state = s::GameState::create_from(&config)?;
+        GameEngine::new().initialize(&mut game_state)?;
+        Ok(game_state)
+    }
+
+    #[test]
+    fn test_redact_for_spectators() -> Result<(), Error> {
+        let game_state = GameState::from(&played_game()?);
+        let first_programmed_state = game_state.rounds[0].programmed_state.as_ref().expect("first round has been programmed");
+        assert!(first_programmed_state.players.iter().all(|p| !p.program_card_deck.is_empty()), "cards left in hand after programming");
+
+        for reveal_hands_after_round in &[false, true] {
+            let mut redacted = game_state.clone();
+            redacted.redact_for_spectators(*reveal_hands_after_round);
+
+            let states = redacted.initial_state.iter()
+                .chain(redacted.start_state.iter())
+                .chain(redacted.rounds.iter().filter_map(|r| r.state.as_ref()));
+            for state in states {
+                assert!(state.cards.is_empty(), "deck hidden");
+                assert!(state.players.iter().all(|p| p.program_card_deck.is_empty()), "hands hidden");
+            }
+            for round in &redacted.rounds {
+                let state = round.state.as_ref().unwrap();
+                if round.phase == ERoundPhase::Programming as i32 {
+                    let registers = state.players.iter().flat_map(|p| p.registers.iter());
+                    assert!(registers.filter(|r| !r.locked).all(|r| r.move_card.is_none()), "registers hidden while programming");
+                }
+                if let Some(programmed_state) = &round.programmed_state {
+                    assert!(programmed_state.cards.is_empty(), "deck hidden");
+                    let players = &programmed_state.players;
+                    assert!(players.iter().all(|p| p.registers.iter().all(|r| r.move_card.is_some())), "played registers shown");
+                    assert_eq!(players.iter().all(|p| !p.program_card_deck.is_empty()), *reveal_hands_after_round, "hands revealed after the round");
+                }
+            }
+        }
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -0,0 +1,376 @@
+use std::cmp::Reverse;
+
+use failure::Fail;
+use rand::seq::{ index, SliceRandom };
+
+use crate::roborally::state::{ EBotLevel, MoveCard, MoveCardID, PlayerID, Position, State, StateError, StartPositionID };
+use super::register_engine::{ ESimpleMove, ESimulationMode, RegisterEngine, RegisterEngineError };
+use super::player_input::{ ProgramInput, StartPositionInput };
+
+#[derive(Debug, Fail)]
+pub enum BotError {
+    #[fail(display = "Bot for player {} found no input: {}", player_id, msg)]
+    NoInputFound {
+        player_id: PlayerID,
+        msg: String,
+    },
+    #[fail(display = "Simulation error: {}", err)]
+    SimulationError {
+        #[cause]
+        err: RegisterEngineError,
+    },
+    #[fail(display = "State error: {}", err)]
+    StateError {
+        #[cause]
+        err: StateError,
+    },
+}
+
+impl From<RegisterEngineError> for BotError {
+    fn from(err: RegisterEngineError) -> Self {
+        BotError::SimulationError{ err }
+    }
+}
+
+impl From<StateError> for BotError {
+    fn from(err: StateError) -> Self {
+        BotError::StateError{ err }
+    }
+}
+
+/// What greedy bots head for. Boards don't have flags yet: a target returning the player's next flag plugs in here once
+/// they do.
+pub trait TBotTarget {
+    /// The position the player's robot should reach. None if there is none: the bot heads for the board center then
+    fn target(&self, state: &State, player_id: PlayerID) -> Option<Position>;
+}
+
+/// The target as long as boards have nothing to head for
+pub struct NoTarget;
+
+impl TBotTarget for NoTarget {
+    fn target(&self, _state: &State, _player_id: PlayerID) -> Option<Position> {
+        None
+    }
+}
+
+/// A built-in player: chooses start positions and programs on its own, depending on its level
+pub struct Bot {
+    level: EBotLevel,
+    target: Box<dyn TBotTarget>,
+    register_engine: RegisterEngine,
+}
+
+impl Bot {
+    pub fn new(level: EBotLevel) -> Bot {
+        Bot::with_target(level, Box::new(NoTarget))
+    }
+
+    pub fn with_target(level: EBotLevel, target: Box<dyn TBotTarget>) -> Bot {
+        Bot {
+            level,
+            target,
+            register_engine: RegisterEngine::new(),
+        }
+    }
+
+    pub fn choose_start_position(&self, state: &State, player_id: PlayerID) -> Result<StartPositionInput, BotError> {
+        let mut free_start_positions: Vec<(StartPositionID, Position)> = state.board.tiles.iter()
+            .filter_map(|t| t.start_position_id.map(|id| (id, t.position)))
+            .filter(|(_, position)| state.get_robot_at_position(position).is_none())
+            .collect();
+        free_start_positions.sort();
+
+        let target = self.target_position(state, player_id);
+        let choice = match self.level {
+            EBotLevel::Random => free_start_positions.choose(&mut rand::thread_rng()),
+            EBotLevel::Greedy | EBotLevel::AvoidDeath => free_start_positions.iter()
+                .min_by_key(|(_, position)| distance(position, &target)),
+        };
+        let (start_position_id, _) = choice.ok_or_else(|| BotError::NoInputFound {
+            player_id,
+            msg: String::from("no free start position left"),
+        })?;
+
+        Ok(StartPositionInput {
+            player_id,
+            start_position_id: *start_position_id,
+        })
+    }
+
+    pub fn choose_program(&self, state: &State, player_id: PlayerID) -> Result<ProgramInput, BotError> {
+        let player = state.get_player_or_fail(player_id)?;
+        let register_count = player.count_unlocked_registers();
+        let cards = &player.program_card_deck;
+        if cards.len() < register_count {
+            return Err(BotError::NoInputFound {
+                player_id,
+                msg: format!("only {} cards for {} registers", cards.len(), register_count),
+            });
+        }
+
+        let register_cards_choices = match self.level {
+            EBotLevel::Random => index::sample(&mut rand::thread_rng(), cards.len(), register_count)
+                .into_iter()
+                .map(|i| cards[i].id)
+                .collect(),
+            EBotLevel::Greedy => self.search_program(state, player_id, false)?,
+            EBotLevel::AvoidDeath => self.search_program(state, player_id, true)?,
+        };
+        Ok(ProgramInput {
+            player_id,
+            register_cards_choices,
+        })
+    }
+
+    /// Searches the programs that can be built from the player's hand and returns the best one found. Each program is
+    /// simulated on a copy of the state in which only this player's robot plays cards.
+    /// Only the SEARCH_WIDTH best programs are extended by another register. This bounds the search to
+    /// registers * SEARCH_WIDTH * hand size simulated registers: the service runs bots while it holds the game.
+    fn search_program(&self, state: &State, player_id: PlayerID, avoid_death: bool) -> Result<Vec<MoveCardID>, BotError> {
+        let player = state.get_player_or_fail(player_id)?;
+
+        // Cards with the same moves are interchangeable (other robots don't move, so priority does not matter):
+        // group them so each distinct program is only simulated once
+        let mut groups: Vec<(Vec<ESimpleMove>, Vec<&MoveCard>)> = vec![];
+        for card in &player.program_card_deck {
+            let moves: Vec<ESimpleMove> = card.tmove.iter().cloned().collect();
+            match groups.iter_mut().find(|(m, _)| *m == moves) {
+                Some((_, cards)) => cards.push(card),
+                None => groups.push((moves, vec![card])),
+            }
+        }
+        let search = ProgramSearch {
+            player_id,
+            register_count: player.count_unlocked_registers(),
+            groups: groups.into_iter().map(|(_, cards)| cards).collect(),
+            locked_cards: player.registers.iter()
+                .filter(|r| r.locked)
+                .filter_map(|r| r.move_card.clone())
+                .collect(),
+            target: self.target_position(state, player_id),
+            avoid_death,
+        };
+
+        let mut candidates = vec![Candidate{ state: Box::from(state.clone()), chosen: vec![], score: 0 }];
+        for register in 0..search.register_count {
+            let mut next = vec![];
+            for candidate in candidates {
+                if candidate.state.get_robot_by_player_id_or_fail(player_id)?.is_destroyed() {
+                    // Its program ends here
+                    next.push(candidate);
+                    continue;
+                }
+                for (group_index, group) in search.groups.iter().enumerate() {
+                    if candidate.chosen.iter().filter(|g| **g == group_index).count() >= group.len() {
+                        continue;
+                    }
+                    let state = self.register_engine.simulate_register_phase(candidate.state.clone(), register, player_id, group[0], ESimulationMode::Idle)?;
+                    let score = search.score(&state)?;
+                    let mut chosen = candidate.chosen.clone();
+                    chosen.push(group_index);
+                    next.push(Candidate{ state, chosen, score });
+                }
+            }
+            // Stable, so the search is deterministic
+            next.sort_by_key(|c| Reverse(c.score));
+            next.truncate(SEARCH_WIDTH);
+            candidates = next;
+        }
+
+        let mut best: Option<(i64, Vec<usize>)> = None;
+        for candidate in candidates {
+            let mut state = candidate.state;
+            if !state.get_robot_by_player_id_or_fail(player_id)?.is_destroyed() {
+                for (i, card) in search.locked_cards.iter().enumerate() {
+                    state = self.register_engine.simulate_register_phase(state, candidate.chosen.len() + i, player_id, card, ESimulationMode::Idle)?;
+                }
+            }
+            let score = search.score(&state)?;
+            if best.as_ref().map_or(true, |(best_score, _)| score > *best_score) {
+                best = Some((score, candidate.chosen));
+            }
+        }
+
+        let chosen_groups = best.map(|(_, groups)| groups).unwrap_or_default();
+        Ok(search.to_card_ids(&chosen_groups))
+    }
+
+    /// The position the bot tries to reach: its target, or the center of the board if there is none
+    fn target_position(&self, state: &State, player_id: PlayerID) -> Position {
+        self.target.target(state, player_id)
+            .unwrap_or_else(|| Position::new(state.board.size_x / 2, state.board.size_y / 2))
+    }
+}
+
+/// The number of programs the search extends by each register
+const SEARCH_WIDTH: usize = 100;
+
+/// A program being built by the search
+struct Candidate {
+    /// After the chosen registers
+    state: Box<State>,
+    /// Group per register
+    chosen: Vec<usize>,
+    score: i64,
+}
+
+struct ProgramSearch<'a> {
+    player_id: PlayerID,
+    register_count: usize,
+    /// The player's hand, grouped by moves
+    groups: Vec<Vec<&'a MoveCard>>,
+    /// Cards in locked registers, played after the chosen ones
+    locked_cards: Vec<MoveCard>,
+    target: Position,
+    avoid_death: bool,
+}
+
+impl<'a> ProgramSearch<'a> {
+    /// Higher is better
+    fn score(&self, state: &State) -> Result<i64, BotError> {
+        let robot = state.get_robot_by_player_id_or_fail(self.player_id)?;
+        if self.avoid_death && robot.is_destroyed() {
+            return Ok(i64::MIN);
+        }
+        Ok(-(distance(&robot.position, &self.target) as i64))
+    }
+
+    /// Maps the chosen groups to card ids. Programs that end early (dead robot) are filled up with any remaining cards.
+    fn to_card_ids(&self, chosen_groups: &[usize]) -> Vec<MoveCardID> {
+        let mut remaining: Vec<Vec<MoveCardID>> = self.groups.iter()
+            .map(|cards| cards.iter().rev().map(|c| c.id).collect())
+            .collect();
+        let mut card_ids: Vec<MoveCardID> = chosen_groups.iter()
+            .filter_map(|group_index| remaining[*group_index].pop())
+            .collect();
+        let mut rest = remaining.into_iter().flatten();
+        while card_ids.len() < self.register_count {
+            match rest.next() {
+                Some(id) => card_ids.push(id),
+                None => break,
+            }
+        }
+        card_ids
+    }
+}
+
+fn distance(a: &Position, b: &Position) -> u32 {
+    ((a.x - b.x).abs() + (a.y - b.y).abs()) as u32
+}
+
+#[cfg(test)]
+mod test {
+    use failure::Error;
+
+    use crate::roborally::state::*;
+    use crate::roborally::engine::register_engine::{ ESimpleMove, RegisterEngine };
+    use super::{ Bot, TBotTarget };
+
+    fn create_state(position: Position, direction: EDirection, hand: &[&[ESimpleMove]]) -> Result<Box<State>, Error> {
+        let robot = RobotBuilder::default()
+            .id(0)
+            .position(position)
+            .direction(direction)
+            .build().unwrap();
+        let player = Player::new_with_move(0, robot, MoveCard::new_from_moves(100, 1, &[]));
+        let mut player = player.set_program_card_deck(hand.iter().enumerate()
+            .map(|(i, moves)| MoveCard::new_from_moves(i as u32, i as u32 + 1, moves))
+            .collect());
+        player.registers = vec![Register::default(); 3];
+
+        let board = Board::load_board_by_name("empty-5x5")?;
+        Ok(State::new_with_random_deck(board, vec![player]))
+    }
+
+    /// Runs the chosen program the way the engine would
+    fn run_program(state: &State, register_cards_choices: &[MoveCardID]) -> Result<Box<State>, Error> {
+        let mut player = state.get_player_or_fail(0)?.clone();
+        for (i, id) in register_cards_choices.iter().enumerate() {
+            player = player.choose_card(i, *id)?;
+        }
+        let state = state.update_player(player)?;
+        Ok(RegisterEngine::new().execute_registers(state)?)
+    }
+
+    #[test]
+    fn test_choose_program_uses_hand() -> Result<(), Error> {
+        use ESimpleMove::*;
+        let hand: &[&[ESimpleMove]] = &[&[Forward], &[Forward], &[TurnLeft], &[TurnRight], &[UTurn], &[Backward]];
+        let state = create_state(Position::new(2, 2), EDirection::NORTH, hand)?;
+
+        for level in &[EBotLevel::Random, EBotLevel::Greedy, EBotLevel::AvoidDeath] {
+            let input = Bot::new(*level).choose_program(&state, 0)?;
+            let mut ids = input.register_cards_choices.clone();
+            ids.sort();
+            ids.dedup();
+            assert_eq!(ids.len(), 3, "{:?}: three distinct cards", level);
+            assert!(ids.iter().all(|id| *id < hand.len() as u32), "{:?}: cards from hand", level);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_greedy_heads_for_center_without_target() -> Result<(), Error> {
+        use ESimpleMove::*;
+        let hand: &[&[ESimpleMove]] = &[&[TurnLeft], &[Forward, Forward], &[UTurn], &[Backward], &[TurnRight]];
+        let state = create_state(Position::new(0, 2), EDirection::NORTH, hand)?;
+
+        let input = Bot::new(EBotLevel::Greedy).choose_program(&state, 0)?;
+        let state = run_program(&state, &input.register_cards_choices)?;
+        assert_eq!(state.get_robot_by_player_id_or_fail(0)?.position, Position::new(2, 2), "reached the center");
+        Ok(())
+    }
+
+    struct FixedTarget(Position);
+
+    impl TBotTarget for FixedTarget {
+        fn target(&self, _state: &State, _player_id: PlayerID) -> Option<Position> {
+            Some(self.0)
+        }
+    }
+
+    #[test]
+    fn test_greedy_heads_for_target() -> Result<(), Error> {
+        use ESimpleMove::*;
+        let hand: &[&[ESimpleMove]] = &[&[TurnLeft], &[Forward, Forward], &[UTurn], &[Backward], &[TurnRight]];
+        let state = create_state(Position::new(2, 2), EDirection::NORTH, hand)?;
+
+        let bot = Bot::with_target(EBotLevel::Greedy, Box::new(FixedTarget(Position::new(4, 2))));
+        let input = bot.choose_program(&state, 0)?;
+        let state = run_program(&state, &input.register_cards_choices)?;
+        assert_eq!(state.get_robot_by_player_id_or_fail(0)?.position, Position::new(4, 2), "reached the target");
+        Ok(())
+    }
+
+    #[test]
+    fn test_avoid_death() -> Result<(), Error> {
+        use ESimpleMove::*;
+        // Any program that starts with a move forward kills the robot, the only way back is the UTurn
+        let hand: &[&[ESimpleMove]] = &[&[Forward], &[Forward], &[Forward], &[UTurn], &[TurnLeft]];
+        let state = create_state(Position::new(2, 0), EDirection::NORTH, hand)?;
+
+        let input = Bot::new(EBotLevel::AvoidDeath).choose_program(&state, 0)?;
+        let state = run_program(&state, &input.register_cards_choices)?;
+        let robot = state.get_robot_by_player_id_or_fail(0)?;
+        assert!(!robot.is_destroyed(), "robot alive: {:?}", input.register_cards_choices);
+        assert_eq!(robot.position, Position::new(2, 2), "robot position");
+        Ok(())
+    }
+
+    #[test]
+    fn test_choose_start_position() -> Result<(), Error> {
+        let mut game_state = GameState::create_from(&GameConfig::default())?;
+        let start_state = game_state.start_state.clone();
+
+        let bot = Bot::new(EBotLevel::Greedy);
+        let input = bot.choose_start_position(&start_state, 0)?;
+        let position = start_state.board.get_start_position_or_fail(input.start_position_id)?;
+
+        // Take it: the next bot has to choose another one
+        game_state.start_state = start_state.update_player_fn(0, |p| p.robot.position = position)?;
+        let other_input = bot.choose_start_position(&game_state.start_state, 1)?;
+        assert_ne!(input.start_position_id, other_input.start_position_id, "different start positions");
+        Ok(())
+    }
+}
\ No newline at end of file
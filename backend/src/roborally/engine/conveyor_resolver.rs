@@ -0,0 +1,192 @@
+//! Decides which of the robots on conveyors actually move in a conveyor step. All robots move at once, so whether a
+//! robot may move depends on whether the robot in front of it moves as well. This is resolved by dropping moves until
+//! the remaining ones are consistent (a fixed point):
+//!  - blocked: a robot may only move onto a tile which is free or vacated in the same step. A blocked robot blocks
+//!    the robots behind it, so whole chains stop at once.
+//!  - conflicts: two robots moving onto the same tile (e.g. merging at a Conveyor3). It's unclear who goes first, so
+//!    neither moves.
+//!  - ghosting: two robots exchanging their positions would have to pass through each other, so neither moves.
+//!
+//! Robots moving in a circle (belt loops of three or more tiles) all move, as every tile is vacated by the robot on it.
+//! Robots falling off the platform never block or conflict: a pit has room for everyone.
+use crate::roborally::state::{ EConnection, EDirection, ERotationDirection, ETileType, Position, RobotID };
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConveyorMove {
+    pub robot_id: RobotID,
+    pub origin: Position,
+    pub outbound_direction: EDirection,
+    pub connection: EConnection,
+}
+
+impl ConveyorMove {
+    pub fn new(robot_id: RobotID, origin: Position, outbound_direction: EDirection, connection: EConnection) -> Self {
+        ConveyorMove { robot_id, origin, outbound_direction, connection }
+    }
+
+    /// The tile the robot would move onto, None if it falls off the platform or is walled in
+    fn target_tile(&self) -> Option<Position> {
+        match self.connection {
+            EConnection::Free(to) => Some(to),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the moves that actually happen. robot_positions are the positions of all active robots, including those
+/// that are not on a conveyor.
+pub fn resolve_conveyor_moves(moves: Vec<ConveyorMove>, robot_positions: &[Position]) -> Vec<ConveyorMove> {
+    // Walled in: the robot stays where it is (and blocks others)
+    let mut moves: Vec<ConveyorMove> = moves.into_iter()
+        .filter(|m| m.connection != EConnection::Walled)
+        .collect();
+
+    loop {
+        let previous_count = moves.len();
+
+        let conflicting = |mov: &ConveyorMove, moves: &[ConveyorMove]| {
+            mov.target_tile().map_or(false, |to| moves.iter().any(|m| m.robot_id != mov.robot_id && m.target_tile() == Some(to)))
+        };
+        let ghosting = |mov: &ConveyorMove, moves: &[ConveyorMove]| {
+            mov.target_tile().map_or(false, |to| moves.iter().any(|m| m.origin == to && m.target_tile() == Some(mov.origin)))
+        };
+        let blocked = |mov: &ConveyorMove, moves: &[ConveyorMove]| {
+            mov.target_tile().map_or(false, |to| robot_positions.contains(&to) && !moves.iter().any(|m| m.origin == to))
+        };
+        let current = moves.clone();
+        moves.retain(|mov| !conflicting(mov, &current) && !ghosting(mov, &current) && !blocked(mov, &current));
+
+        if moves.len() == previous_count {
+            return moves;
+        }
+    }
+}
+
+/// How a robot moved by a belt in direction_of_travel is rotated when it arrives on a tile of the given type:
+/// belts turn it by 90° if it enters a curve (or the side input of a merge) through one of the inputs. Entering a
+/// belt from a side that isn't an input, leaving a curve or being pushed onto a curve by a move doesn't rotate.
+/// Gears turn robots later on, once all conveyors have moved.
+pub fn arrival_rotation(tile_type: ETileType, direction_of_travel: EDirection) -> Option<ERotationDirection> {
+    let inbound_direction = direction_of_travel.turn_around();
+    let (inputs, out): (&[EDirection], EDirection) = match &tile_type {
+        ETileType::Conveyor2{ input, out, .. } => (std::slice::from_ref(input), *out),
+        ETileType::Conveyor3{ inputs, out, .. } => (inputs.as_slice(), *out),
+        _ => return None,
+    };
+    if !inputs.contains(&inbound_direction) {
+        return None;
+    }
+    direction_of_travel.try_rotate_towards(&out)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::roborally::state::{ EConnection, EDirection, ERotationDirection, Position, tile_type_from_glyph };
+    use super::{ arrival_rotation, resolve_conveyor_moves, ConveyorMove };
+
+    fn free(robot_id: u32, from: (i32, i32), direction: EDirection) -> ConveyorMove {
+        let origin = Position::new(from.0, from.1);
+        ConveyorMove::new(robot_id, origin, direction, EConnection::Free(origin.neighbor_in(direction)))
+    }
+
+    fn moved_robot_ids(moves: &[ConveyorMove], robot_positions: &[(i32, i32)]) -> Vec<u32> {
+        let robot_positions: Vec<Position> = robot_positions.iter().map(|(x, y)| Position::new(*x, *y)).collect();
+        let mut ids: Vec<u32> = resolve_conveyor_moves(moves.to_vec(), &robot_positions).iter()
+            .map(|m| m.robot_id)
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    #[test]
+    fn test_chain_moves_together() {
+        use EDirection::*;
+        let moves = [free(0, (0, 0), EAST), free(1, (1, 0), EAST), free(2, (2, 0), EAST)];
+        assert_eq!(moved_robot_ids(&moves, &[(0, 0), (1, 0), (2, 0)]), vec![0, 1, 2], "whole chain moves");
+    }
+
+    #[test]
+    fn test_blocked_chain() {
+        use EDirection::*;
+        // Robot 3 stands on a regular tile at the end of the belt
+        let moves = [free(0, (0, 0), EAST), free(1, (1, 0), EAST), free(2, (2, 0), EAST)];
+        assert_eq!(moved_robot_ids(&moves, &[(0, 0), (1, 0), (2, 0), (3, 0)]), Vec::<u32>::new(), "static robot blocks the chain");
+
+        // Robot 2 is walled in at the end of the belt
+        let walled = ConveyorMove::new(2, Position::new(2, 0), EAST, EConnection::Walled);
+        let moves = [free(0, (0, 0), EAST), free(1, (1, 0), EAST), walled];
+        assert_eq!(moved_robot_ids(&moves, &[(0, 0), (1, 0), (2, 0)]), Vec::<u32>::new(), "walled robot blocks the chain");
+
+        // A gap in the chain: only the part behind the blocked robot stops
+        let moves = [free(0, (0, 0), EAST), free(1, (2, 0), EAST)];
+        assert_eq!(moved_robot_ids(&moves, &[(0, 0), (2, 0), (3, 0)]), vec![0], "gap in chain");
+    }
+
+    #[test]
+    fn test_belt_loop() {
+        use EDirection::*;
+        // Four robots on a 2x2 loop, all moving clockwise
+        let moves = [free(0, (0, 0), EAST), free(1, (1, 0), SOUTH), free(2, (1, 1), WEST), free(3, (0, 1), NORTH)];
+        assert_eq!(moved_robot_ids(&moves, &[(0, 0), (1, 0), (1, 1), (0, 1)]), vec![0, 1, 2, 3], "full loop");
+
+        // A loop with a free tile moves as well
+        let moves = [free(0, (0, 0), EAST), free(1, (1, 0), SOUTH), free(2, (1, 1), WEST)];
+        assert_eq!(moved_robot_ids(&moves, &[(0, 0), (1, 0), (1, 1)]), vec![0, 1, 2], "loop with gap");
+    }
+
+    #[test]
+    fn test_ghosting() {
+        use EDirection::*;
+        let moves = [free(0, (0, 0), EAST), free(1, (1, 0), WEST), free(2, (2, 0), WEST)];
+        assert_eq!(moved_robot_ids(&moves, &[(0, 0), (1, 0), (2, 0)]), Vec::<u32>::new(), "swap blocks the robot behind as well");
+    }
+
+    #[test]
+    fn test_merge_conflict() {
+        use EDirection::*;
+        // Two robots entering a Conveyor3 at (1, 1) from both sides: unclear, so nobody moves
+        let moves = [free(0, (0, 1), EAST), free(1, (2, 1), WEST), free(2, (3, 1), WEST)];
+        assert_eq!(moved_robot_ids(&moves, &[(0, 1), (2, 1), (3, 1)]), Vec::<u32>::new(), "merge conflict blocks both belts");
+
+        // The merge tile is vacated by the robot on it: still unclear who follows
+        let moves = [free(0, (0, 1), EAST), free(1, (2, 1), WEST), free(2, (1, 1), SOUTH)];
+        assert_eq!(moved_robot_ids(&moves, &[(0, 1), (2, 1), (1, 1)]), vec![2], "only the robot on the merge tile moves");
+    }
+
+    #[test]
+    fn test_off_platform() {
+        use EDirection::*;
+        let pit = Position::new(1, 0);
+        let moves = [
+            ConveyorMove::new(0, Position::new(0, 0), EAST, EConnection::OffPlatform(pit)),
+            ConveyorMove::new(1, Position::new(2, 0), WEST, EConnection::OffPlatform(pit)),
+        ];
+        assert_eq!(moved_robot_ids(&moves, &[(0, 0), (2, 0)]), vec![0, 1], "both fall into the pit");
+    }
+
+    #[test]
+    fn test_arrival_rotation() {
+        use EDirection::*;
+        use ERotationDirection::*;
+        let cases = [
+            // (glyph, direction of travel, expected rotation)
+            ('┕', SOUTH, Some(Left)),   // curve: input NORTH, out EAST
+            ('┕', WEST, None),          // from the side that is its exit
+            ('┍', NORTH, Some(Right)),  // curve: input SOUTH, out EAST
+            ('╜', EAST, Some(Left)),    // express curve: input WEST, out NORTH
+            ('→', EAST, None),          // straight
+            ('↡', WEST, None),          // straight, entered from the side
+            ('┸', EAST, Some(Left)),    // merge: inputs WEST and EAST, out NORTH
+            ('┸', WEST, Some(Right)),
+            ('┝', SOUTH, Some(Left)),   // merge: inputs NORTH and SOUTH, out EAST
+            ('┝', EAST, None),          // not an input
+            ('┞', NORTH, None),         // merge: inputs EAST and SOUTH, out NORTH, straight input
+            ('↻', EAST, None),          // gears rotate later on
+            ('o', EAST, None),
+        ];
+        for (glyph, direction, expected) in &cases {
+            let tile_type = tile_type_from_glyph(*glyph).unwrap();
+            assert_eq!(arrival_rotation(tile_type, *direction), *expected, "arriving on '{}' travelling {:?}", glyph, direction);
+        }
+    }
+}
\ No newline at end of file
@@ -1,4 +1,4 @@
-use failure::{ Fail, Error };
+use failure::Fail;
 
 use crate::roborally::state::{
     GameState,
@@ -11,11 +11,11 @@ use crate::roborally::state::{
     ERoundPhase,
     EGamePhase,
     EGameResult,
-    MAX_DAMAGE_TOKENS,
     StartPositionID,
-    Position,
+    EBotLevel,
 };
 use super::register_engine::{ RegisterEngine, RegisterEngineError };
+use super::bot::{ Bot, BotError };
 use super::player_input::{ ProgramInput, StartPositionInput };
 
 #[derive(Debug, Fail)]
@@ -30,15 +30,6 @@ pub enum EngineError {
         player_id: PlayerID,
         start_position_id: StartPositionID,
     },
-    #[fail(display = "Start position {:?} already taken. {}", start_position, player_id)]
-    StartPositionAlreadyTaken {
-        player_id: PlayerID,
-        start_position: Position,
-    },
-    #[fail(display = "Engine error: {}", msg)]
-    GenericAlgorithmError {
-        msg: String,
-    },
     #[fail(display = "Invalid round phase! Expected: {:?}, found: {:?}", expected, actual)]
     InvalidRoundPhase {
         expected: ERoundPhase,
@@ -48,22 +39,43 @@ pub enum EngineError {
     InvalidGamePhase {
         expected: EGamePhase,
         actual: EGamePhase,
-    }
+    },
+    #[fail(display = "State error: {}", err)]
+    StateError {
+        #[cause]
+        err: StateError,
+    },
+    #[fail(display = "Register engine error: {}", err)]
+    RegisterEngineError {
+        #[cause]
+        err: RegisterEngineError,
+    },
+    #[fail(display = "Bot error: {}", err)]
+    BotError {
+        #[cause]
+        err: BotError,
+    },
 }
 
-// TODO this is dump. Maybe move all errors into one enum? Or is there a better way to chain errors?
 impl From<StateError> for EngineError {
     fn from(err: StateError) -> EngineError {
-        EngineError::GenericAlgorithmError{ msg: format!{"{}", err} }
+        EngineError::StateError{ err }
     }
 }
 
 impl From<RegisterEngineError> for EngineError {
     fn from(err: RegisterEngineError) -> EngineError {
-        EngineError::GenericAlgorithmError{ msg: format!{"{}", err} }
+        EngineError::RegisterEngineError{ err }
     }
 }
 
+impl From<BotError> for EngineError {
+    fn from(err: BotError) -> EngineError {
+        EngineError::BotError{ err }
+    }
+}
+
+#[derive(Default)]
 pub struct GameEngine {
     pub game_engine: RoundEngine,
 }
@@ -75,21 +87,23 @@ impl GameEngine {
         }
     }
 
-    pub fn initialize(&self, game_state: &mut GameState) -> Result<(), Error> {
+    pub fn initialize(&self, game_state: &mut GameState) -> Result<(), EngineError> {
         assert_game_phase(&game_state, EGamePhase::INITIAL)?;
 
         // Choose start positions (ordered by player precedence)
         let fist_player_id = game_state.first_player_id_by_precedence();
-        game_state.start_state = game_state.start_state.update_player_fn(fist_player_id, |p| {
-            p.input_required = true;
-            Ok(())
-        })?;
+        game_state.start_state = game_state.start_state.update_player_fn(fist_player_id, |p| p.input_required = true)?;
 
         game_state.phase = EGamePhase::PREPARATION;
-        Ok(())
+        self.run_bots(game_state)
+    }
+
+    pub fn set_start_position(&self, game_state: &mut GameState, input: &StartPositionInput) -> Result<(), EngineError> {
+        self.apply_start_position(game_state, input)?;
+        self.run_bots(game_state)
     }
 
-    pub fn set_start_position(&self, game_state: &mut GameState, input: &StartPositionInput) -> Result<(), Error> {
+    fn apply_start_position(&self, game_state: &mut GameState, input: &StartPositionInput) -> Result<(), EngineError> {
         assert_game_phase(&game_state, EGamePhase::PREPARATION)?;
 
         // Set start position
@@ -97,10 +111,7 @@ impl GameEngine {
 
         // Mark next as active
         if let Some(next_player_id) = game_state.next_player_id_by_precedence(input.player_id) {
-            game_state.start_state = game_state.start_state.update_player_fn(next_player_id, |p| {
-                p.input_required = true;
-                Ok(())
-            })?;
+            game_state.start_state = game_state.start_state.update_player_fn(next_player_id, |p| p.input_required = true)?;
             // let mut new_player: Player;
             // for player in game_state.start_state.all_players() {
             //     if player.id == next_player_id {
@@ -120,21 +131,27 @@ impl GameEngine {
         Ok(())
     }
 
-    fn do_set_start_position(&self, state: &State, input: &StartPositionInput) -> Result<Box<State>, Error> {
+    /// Robots may start on a start position which is already taken: all robots on it are virtual then, until they
+    /// separate
+    fn do_set_start_position(&self, state: &State, input: &StartPositionInput) -> Result<Box<State>, EngineError> {
         // TODO Move this into state
-        let state = state.update_player_fn(input.player_id, |player| {
+        let start_position = state.board.get_start_position_or_fail(input.start_position_id)?;
+        let sharing_player_ids: Vec<PlayerID> = state.all_players()
+            .filter(|p| p.id != input.player_id && p.robot.position == start_position)
+            .map(|p| p.id)
+            .collect();
+        let mut state = state.try_update_player_fn(input.player_id, |player| {
             if !player.input_required {
-                return Err(EngineError::InvalidStartPositionInput{ player_id: player.id, start_position_id: input.start_position_id }.into());
+                return Err(EngineError::InvalidStartPositionInput{ player_id: player.id, start_position_id: input.start_position_id });
             }
             player.input_required = false;
-
-            let start_position = state.board.get_start_position_or_fail(input.start_position_id)?;
-            if state.get_robot_at_position(&start_position).is_some() {
-                return Err(EngineError::StartPositionAlreadyTaken{ player_id: player.id, start_position }.into());
-            }
             player.robot.position = start_position;
+            player.robot.is_virtual = !sharing_player_ids.is_empty();
             Ok(())
         })?;
+        for player_id in sharing_player_ids {
+            state = state.update_player_fn(player_id, |p| p.robot.is_virtual = true)?;
+        }
         // let player = state.get_player_or_fail(input.player_id)?;
         // if !player.input_required {
         //     return Err(EngineError::InvalidStartPositionInput{ player_id: player.id, start_position_id: input.start_position_id });
@@ -148,7 +165,12 @@ impl GameEngine {
         Ok(state)
     }
 
-    pub fn set_player_program_input(&self, game_state: &mut GameState, input: &ProgramInput) -> Result<(), Error> {
+    pub fn set_player_program_input(&self, game_state: &mut GameState, input: &ProgramInput) -> Result<(), EngineError> {
+        self.apply_player_program_input(game_state, input)?;
+        self.run_bots(game_state)
+    }
+
+    fn apply_player_program_input(&self, game_state: &mut GameState, input: &ProgramInput) -> Result<(), EngineError> {
         assert_game_phase(&game_state, EGamePhase::RUNNING)?;
         let round = game_state.current_round()?;
         let mut round = self.game_engine.set_player_program_input(round, input)?;
@@ -171,8 +193,50 @@ impl GameEngine {
         }
         Ok(())
     }
+
+    /// Lets bots provide their input until a human has to act. Bots program at most one round per call, so a game
+    /// played by bots only does not run away: call this again to play its next round.
+    pub fn run_bots(&self, game_state: &mut GameState) -> Result<(), EngineError> {
+        // Start positions are chosen one after another
+        while game_state.phase == EGamePhase::PREPARATION {
+            let (player_id, level) = match game_state.start_state.all_players().find(|p| p.input_required) {
+                Some(player) => (player.id, player.bot_level()),
+                None => return Ok(()),
+            };
+            let level = match level {
+                Some(level) => level,
+                None => return Ok(()),  // A human's turn
+            };
+            let input = Bot::new(level).choose_start_position(&game_state.start_state, player_id)?;
+            self.apply_start_position(game_state, &input)?;
+        }
+
+        if game_state.phase != EGamePhase::RUNNING || game_state.game_result.is_some() {
+            return Ok(());
+        }
+        let round = game_state.current_round()?;
+        if round.phase != ERoundPhase::PROGRAMMING {
+            return Ok(());
+        }
+        let round_id = round.id;
+        let bots: Vec<(PlayerID, EBotLevel)> = round.state.active_players()
+            .filter(|p| !p.has_programmed_registers())
+            .filter_map(|p| p.bot_level().map(|level| (p.id, level)))
+            .collect();
+        for (player_id, level) in bots {
+            let round = game_state.current_round()?;
+            if round.id != round_id {
+                // The last input finished the round
+                break;
+            }
+            let input = Bot::new(level).choose_program(&round.state, player_id)?;
+            self.apply_player_program_input(game_state, &input)?;
+        }
+        Ok(())
+    }
 }
 
+#[derive(Default)]
 pub struct RoundEngine {
     pub register_engine: RegisterEngine,
 }
@@ -184,7 +248,7 @@ impl RoundEngine {
         }
     }
     
-    fn run_round_initialization(&self, round: &Round) -> Result<Round, Error> {
+    fn run_round_initialization(&self, round: &Round) -> Result<Round, EngineError> {
         assert_round_phase(&round, ERoundPhase::INITIALIZATION)?;
         let mut state = round.state.clone();
 
@@ -205,16 +269,13 @@ impl RoundEngine {
             }
         }
 
+        //    - which unlocks its registers: their cards are discarded
+        state = state.lock_registers_according_to_damage();
+        state = state.discard_program_cards()?;
+
         // 1. Deal Program Cards:
-        //  - draw 9 cards randomly (- damage tokens) cards
-        let player_it = state.active_players_cloned();
-        for player in player_it.iter() {
-            let cards_to_draw = MAX_DAMAGE_TOKENS - player.robot.damage;
-            let (deck, cards) = state.deck.take_random_cards(cards_to_draw);
-            let new_player = player.set_program_card_deck(cards);
-            state = state.update_player(new_player)?;
-            state = state.set_deck(deck);
-        }
+        //  - draw 9 cards randomly (- damage tokens) cards, locked registers keep theirs
+        state = state.deal_program_cards()?;
 
         Ok(round.advance(state, ERoundPhase::PROGRAMMING))
     }
@@ -256,10 +317,14 @@ impl RoundEngine {
             });
         }
 
+        // Locked registers keep their card, the choices fill the others in order
+        let unlocked_register_indices = player.registers.iter()
+            .enumerate()
+            .filter(|(_, r)| !r.locked)
+            .map(|(i, _)| i);
         let mut new_player = player.clone();
-        for i in 0..input.register_cards_choices.len() {
-            let move_card_id = input.register_cards_choices[i];
-            new_player = new_player.choose_card(i, move_card_id)?;
+        for (i, move_card_id) in unlocked_register_indices.zip(input.register_cards_choices.iter()) {
+            new_player = new_player.choose_card(i, *move_card_id)?;
         }
         Ok(new_player)
     }
@@ -303,14 +368,8 @@ impl RoundEngine {
         // adjust registers locks according to damage
         state = state.lock_registers_according_to_damage();
 
-        //  - discard all program cards from registers that aren't locked
-        let player_it = state.active_players_cloned();
-        for player in player_it.iter() {
-            let (cards, new_player) = player.take_program_cards_from_unlocked_registers();
-            let new_deck = state.deck.add_cards(cards);
-            state = state.set_deck(new_deck);
-            state = state.update_player(new_player)?;
-        }
+        //  - discard all program cards from registers that aren't locked, and those left in hand
+        state = state.discard_program_cards()?;
 
         Ok(round.advance(state, ERoundPhase::DONE))
     }
@@ -340,4 +399,144 @@ fn assert_round_phase(round: &Round, expected: ERoundPhase) -> Result<(), Engine
         });
     }
     Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use failure::Error;
+
+    use crate::roborally::state::*;
+    use super::{ EngineError, GameEngine };
+    use super::super::bot::BotError;
+    use super::super::register_engine::RegisterEngineError;
+    use super::super::player_input::{ ProgramInput, StartPositionInput };
+
+    fn create_game_state(seats: Vec<ESeat>) -> Result<GameState, Error> {
+        let config = GameConfig {
+            player_config: PlayerConfig::with_seats(seats),
+            ..GameConfig::default()
+        };
+        GameState::create_from(&config)
+    }
+
+    #[test]
+    fn test_bots_only() -> Result<(), Error> {
+        let mut game_state = create_game_state(vec![
+            ESeat::Bot{ level: EBotLevel::Random },
+            ESeat::Bot{ level: EBotLevel::AvoidDeath },
+        ])?;
+        let engine = GameEngine::new();
+        engine.initialize(&mut game_state)?;
+
+        // Bots choose start positions and play exactly one round per call
+        assert_eq!(game_state.phase, EGamePhase::RUNNING, "game phase");
+        assert!(game_state.start_state.all_players().all(|p| p.robot.position != INITIAL_ROBOT_POSITION), "start positions set");
+        for round_count in 2..6 {
+            if game_state.game_result.is_some() {
+                return Ok(());
+            }
+            assert_eq!(game_state.all_rounds().count(), round_count, "rounds played");
+            assert_eq!(game_state.current_round()?.phase, ERoundPhase::PROGRAMMING, "next round waits for programs");
+            engine.run_bots(&mut game_state)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_bot_and_human() -> Result<(), Error> {
+        let mut game_state = create_game_state(vec![
+            ESeat::Human,
+            ESeat::Bot{ level: EBotLevel::Greedy },
+        ])?;
+        let engine = GameEngine::new();
+        engine.initialize(&mut game_state)?;
+
+        // The bot only ever waits for the human
+        let human = game_state.start_state.get_player_or_fail(0)?;
+        assert!(human.input_required, "human has to choose a start position");
+        let start_position_id = game_state.start_state.board.tiles.iter()
+            .filter(|t| t.start_position_id.is_some() && game_state.start_state.get_robot_at_position(&t.position).is_none())
+            .find_map(|t| t.start_position_id)
+            .unwrap();
+        engine.set_start_position(&mut game_state, &StartPositionInput{ player_id: 0, start_position_id })?;
+        assert_eq!(game_state.phase, EGamePhase::RUNNING, "game phase");
+
+        let round = game_state.current_round()?;
+        assert_eq!(round.phase, ERoundPhase::PROGRAMMING, "round phase");
+        assert!(round.state.get_player_or_fail(1)?.has_programmed_registers(), "bot programmed");
+        assert!(!round.state.get_player_or_fail(0)?.has_programmed_registers(), "human did not program");
+
+        let human = round.state.get_player_or_fail(0)?;
+        let register_cards_choices = human.program_card_deck.iter()
+            .take(human.count_unlocked_registers())
+            .map(|c| c.id)
+            .collect();
+        engine.set_player_program_input(&mut game_state, &ProgramInput{ player_id: 0, register_cards_choices })?;
+        if !game_state.game_result.is_some() {
+            assert_eq!(game_state.all_rounds().count(), 2, "round played");
+            assert!(game_state.current_round()?.state.get_player_or_fail(1)?.has_programmed_registers(), "bot programmed again");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_shared_start_position() -> Result<(), Error> {
+        let mut game_state = create_game_state(vec![ESeat::Human, ESeat::Human])?;
+        let engine = GameEngine::new();
+        engine.initialize(&mut game_state)?;
+
+        // Both choose the same start position
+        let start_position_id = game_state.start_state.board.tiles.iter()
+            .find_map(|t| t.start_position_id)
+            .unwrap();
+        for _ in 0..2 {
+            let player_id = game_state.start_state.all_players().find(|p| p.input_required).unwrap().id;
+            engine.set_start_position(&mut game_state, &StartPositionInput{ player_id, start_position_id })?;
+        }
+        assert_eq!(game_state.phase, EGamePhase::RUNNING, "game phase");
+        let round = game_state.current_round()?;
+        assert!(round.state.all_players().all(|p| p.robot.is_virtual), "robots on the same start position are virtual");
+
+        for player_id in 0..2 {
+            let player = game_state.current_round()?.state.get_player_or_fail(player_id)?;
+            let register_cards_choices = player.program_card_deck.iter()
+                .take(player.count_unlocked_registers())
+                .map(|c| c.id)
+                .collect();
+            engine.set_player_program_input(&mut game_state, &ProgramInput{ player_id, register_cards_choices })?;
+        }
+
+        // Robots stay virtual exactly as long as they share their tile
+        let state = &game_state.all_rounds().next().unwrap().state;
+        let robots: Vec<&Robot> = state.active_players().map(|p| &p.robot).collect();
+        for robot in &robots {
+            let shared = robots.iter().any(|r| r.id != robot.id && r.position == robot.position);
+            assert_eq!(robot.is_virtual, shared, "robot {} virtual while sharing its tile", robot.id);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_errors_keep_their_variant() -> Result<(), Error> {
+        let mut game_state = create_game_state(vec![ESeat::Human])?;
+        let engine = GameEngine::new();
+        engine.initialize(&mut game_state)?;
+
+        let err = engine.set_player_program_input(&mut game_state, &ProgramInput{ player_id: 0, register_cards_choices: vec![] }).unwrap_err();
+        assert!(matches!(err, EngineError::InvalidGamePhase{ expected: EGamePhase::RUNNING, .. }), "{}", err);
+
+        let start_position_id = game_state.start_state.board.tiles.iter()
+            .find_map(|t| t.start_position_id)
+            .unwrap();
+        engine.set_start_position(&mut game_state, &StartPositionInput{ player_id: 0, start_position_id })?;
+        let register_cards_choices = vec![9999; REGISTER_COUNT];
+        let err = engine.set_player_program_input(&mut game_state, &ProgramInput{ player_id: 0, register_cards_choices }).unwrap_err();
+        assert!(matches!(err, EngineError::StateError{ err: StateError::InvalidProgramCardChoice{ player_id: 0, move_card_id: 9999, .. } }), "{}", err);
+        assert!(matches!(StateError::find_in(&err), Some(StateError::InvalidProgramCardChoice{ .. })), "state error");
+
+        let err = StateError::PlayerNotFound{ player_id: 3 };
+        let err = EngineError::BotError{ err: BotError::SimulationError{ err: RegisterEngineError::StateError{ err } } };
+        assert!(matches!(StateError::find_in(&err), Some(StateError::PlayerNotFound{ player_id: 3 })), "state error of a bot");
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -1,3 +1,10 @@
 pub mod player_input;
 pub mod register_engine;
-pub mod game_engine;
\ No newline at end of file
+pub mod game_engine;
+pub mod bot;
+pub mod program_simulation;
+pub mod conveyor_resolver;
+#[cfg(test)]
+mod scenario;
+#[cfg(test)]
+mod properties;
\ No newline at end of file
@@ -0,0 +1,234 @@
+use failure::Fail;
+
+use std::collections::HashSet;
+
+use crate::roborally::state::{ EDirection, MoveCard, MoveCardID, PlayerID, Position, State, StateError };
+use super::register_engine::{ ESimulationMode, RegisterEngine, RegisterEngineError };
+
+#[derive(Debug, Fail)]
+pub enum SimulationError {
+    #[fail(display = "Invalid program for player {}: {}", player_id, msg)]
+    InvalidProgram {
+        player_id: PlayerID,
+        msg: String,
+    },
+    #[fail(display = "Simulation error: {}", err)]
+    RegisterEngineError {
+        #[cause]
+        err: RegisterEngineError,
+    },
+    #[fail(display = "State error: {}", err)]
+    StateError {
+        #[cause]
+        err: StateError,
+    },
+}
+
+impl From<RegisterEngineError> for SimulationError {
+    fn from(err: RegisterEngineError) -> Self {
+        SimulationError::RegisterEngineError{ err }
+    }
+}
+
+impl From<StateError> for SimulationError {
+    fn from(err: StateError) -> Self {
+        SimulationError::StateError{ err }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ESimulationEvent {
+    /// Another robot got pushed by the simulated one
+    RobotPushed {
+        player_id: PlayerID,
+        from: Position,
+        to: Position,
+    },
+    RobotDied {
+        player_id: PlayerID,
+        position: Position,
+    },
+}
+
+/// Where the simulated robot is after a register phase, and what happened on the way
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulatedRegister {
+    pub register_index: usize,
+    pub position: Position,
+    pub direction: EDirection,
+    pub destroyed: bool,
+    pub events: Vec<ESimulationEvent>,
+}
+
+/// Runs a tentative program of the given player on a copy of state, without touching state itself.
+/// The chosen cards fill the unlocked registers in order (the choice may be incomplete), locked registers play the
+/// cards they hold. Only the given player plays cards, mode decides what the other robots do.
+pub fn simulate_program(state: &State, player_id: PlayerID, register_cards_choices: &[MoveCardID], mode: ESimulationMode) -> Result<Vec<SimulatedRegister>, SimulationError> {
+    let program = build_program(state, player_id, register_cards_choices)?;
+    let engine = RegisterEngine::new();
+
+    let mut state = Box::from(state.clone());
+    let mut registers = Vec::with_capacity(program.len());
+    for (register_index, card) in program.into_iter().enumerate() {
+        let before = state.clone();
+
+        let player_move_cards = card.into_iter().map(|c| (player_id, c)).collect();
+        state = engine.perform_card_moves(state, player_move_cards)?;
+        let mut events = pushed_robots(&before, &state, player_id)?;
+
+        let board_moved_player_ids = mode.board_moved_player_ids(&state, player_id);
+        state = engine.perform_board_element_moves(state, register_index, &board_moved_player_ids)?;
+        events.extend(died_robots(&before, &state)?);
+
+        let robot = state.get_robot_by_player_id_or_fail(player_id)?;
+        registers.push(SimulatedRegister {
+            register_index,
+            position: robot.position,
+            direction: robot.direction,
+            destroyed: robot.is_destroyed(),
+            events,
+        });
+    }
+    Ok(registers)
+}
+
+/// The card of each register (in order), None for registers the player did not choose a card for (yet)
+fn build_program(state: &State, player_id: PlayerID, register_cards_choices: &[MoveCardID]) -> Result<Vec<Option<MoveCard>>, SimulationError> {
+    let player = state.get_player_or_fail(player_id)?;
+    let invalid = |msg: String| SimulationError::InvalidProgram{ player_id, msg };
+
+    let unlocked_registers_count = player.count_unlocked_registers();
+    if register_cards_choices.len() > unlocked_registers_count {
+        return Err(invalid(format!("Got more program cards ({}) than unlocked registers ({})!", register_cards_choices.len(), unlocked_registers_count)));
+    }
+    let mut seen: HashSet<MoveCardID> = HashSet::new();
+    let mut chosen_cards = Vec::with_capacity(register_cards_choices.len());
+    for move_card_id in register_cards_choices {
+        if !seen.insert(*move_card_id) {
+            return Err(invalid(format!("Card {} chosen twice", move_card_id)));
+        }
+        let card = player.program_card_deck.iter()
+            .find(|c| c.id == *move_card_id)
+            .ok_or_else(|| invalid(format!("Card {} is not in hand", move_card_id)))?;
+        chosen_cards.push(card.clone());
+    }
+
+    let mut chosen_cards = chosen_cards.into_iter();
+    let program = player.registers.iter()
+        .map(|r| if r.locked {
+            r.move_card.clone()
+        } else {
+            chosen_cards.next()
+        })
+        .collect();
+    Ok(program)
+}
+
+fn pushed_robots(before: &State, after: &State, player_id: PlayerID) -> Result<Vec<ESimulationEvent>, SimulationError> {
+    let mut events = vec![];
+    for player in before.active_players().filter(|p| p.id != player_id) {
+        let from = player.robot.position;
+        let to = after.get_robot_by_player_id_or_fail(player.id)?.position;
+        if from != to {
+            events.push(ESimulationEvent::RobotPushed{ player_id: player.id, from, to });
+        }
+    }
+    Ok(events)
+}
+
+fn died_robots(before: &State, after: &State) -> Result<Vec<ESimulationEvent>, SimulationError> {
+    let mut events = vec![];
+    for player in before.all_players().filter(|p| !p.robot.is_destroyed()) {
+        let robot = after.get_robot_by_player_id_or_fail(player.id)?;
+        if robot.is_destroyed() {
+            events.push(ESimulationEvent::RobotDied{ player_id: player.id, position: robot.position });
+        }
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod test {
+    use failure::Error;
+
+    use crate::roborally::state::*;
+    use crate::roborally::engine::register_engine::{ ESimpleMove, ESimulationMode };
+    use super::{ simulate_program, ESimulationEvent, SimulationError };
+
+    fn create_player(id: PlayerID, position: Position, direction: EDirection, hand: &[&[ESimpleMove]]) -> Player {
+        let robot = RobotBuilder::default()
+            .id(id)
+            .position(position)
+            .direction(direction)
+            .build().unwrap();
+        let mut player = Player::new_with_move(id, robot, MoveCard::new_from_moves(100 + id, 1, &[]));
+        player.registers = vec![Register::default(); 3];
+        player.set_program_card_deck(hand.iter().enumerate()
+            .map(|(i, moves)| MoveCard::new_from_moves(i as u32, i as u32 + 1, moves))
+            .collect())
+    }
+
+    #[test]
+    fn test_simulate_program_path() -> Result<(), Error> {
+        use ESimpleMove::*;
+        let player0 = create_player(0, Position::new(2, 2), EDirection::NORTH, &[&[Forward], &[TurnRight], &[Forward, Forward, Forward]]);
+        let player1 = create_player(1, Position::new(2, 0), EDirection::SOUTH, &[]);
+        let state = State::new_with_random_deck(Board::load_board_by_name("empty-5x5")?, vec![player0, player1]);
+
+        let registers = simulate_program(&state, 0, &[0, 2], ESimulationMode::Static)?;
+        assert_eq!(registers.len(), 3, "one entry per register");
+
+        assert_eq!(registers[0].position, Position::new(2, 1), "register 0 position");
+        assert_eq!(registers[0].direction, EDirection::NORTH, "register 0 direction");
+        assert!(registers[0].events.is_empty(), "register 0 events");
+
+        assert!(registers[1].destroyed, "register 1 destroyed");
+        assert_eq!(registers[1].events, vec![
+            ESimulationEvent::RobotPushed{ player_id: 1, from: Position::new(2, 0), to: Position::new(2, -1) },
+            ESimulationEvent::RobotDied{ player_id: 0, position: Position::new(2, -1) },
+            ESimulationEvent::RobotDied{ player_id: 1, position: Position::new(2, -1) },
+        ], "register 1 events");
+
+        assert!(registers[2].destroyed, "register 2 destroyed");
+        assert!(registers[2].events.is_empty(), "register 2 events");
+
+        // The state itself is untouched
+        assert_eq!(state.get_robot_by_player_id_or_fail(0)?.position, Position::new(2, 2), "robot 0 not moved");
+        assert_eq!(state.get_robot_by_player_id_or_fail(1)?.position, Position::new(2, 0), "robot 1 not moved");
+        Ok(())
+    }
+
+    #[test]
+    fn test_simulate_program_modes() -> Result<(), Error> {
+        use ESimpleMove::*;
+        let player0 = create_player(0, Position::new(1, 2), EDirection::NORTH, &[&[UTurn], &[Forward]]);
+        let player1 = create_player(1, Position::new(3, 3), EDirection::WEST, &[]);
+        let state = State::new_with_random_deck(Board::load_board_by_name("test-conveyor-moves")?, vec![player0, player1]);
+
+        // Static: the other robot stays on its conveyor, out of our way
+        let registers = simulate_program(&state, 0, &[0, 1], ESimulationMode::Static)?;
+        assert!(registers.iter().all(|r| r.events.is_empty()), "static: no events");
+
+        // Idle: the conveyors move the other robot right in front of us
+        let registers = simulate_program(&state, 0, &[0, 1], ESimulationMode::Idle)?;
+        assert_eq!(registers[1].events, vec![
+            ESimulationEvent::RobotPushed{ player_id: 1, from: Position::new(1, 3), to: Position::new(1, 4) },
+        ], "idle: robot pushed");
+        Ok(())
+    }
+
+    #[test]
+    fn test_simulate_invalid_program() -> Result<(), Error> {
+        use ESimpleMove::*;
+        let player0 = create_player(0, Position::new(2, 2), EDirection::NORTH, &[&[Forward], &[TurnRight]]);
+        let state = State::new_with_random_deck(Board::load_board_by_name("empty-5x5")?, vec![player0]);
+
+        for choices in &[vec![0, 0], vec![5], vec![0, 1, 0, 1]] {
+            match simulate_program(&state, 0, choices, ESimulationMode::Static) {
+                Err(SimulationError::InvalidProgram{ .. }) => (),
+                other => panic!("Expected InvalidProgram for {:?}, got: {:?}", choices, other),
+            }
+        }
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,237 @@
+//! Property tests for the register engine: random boards, robots and programs, with invariants checked after every
+//! register phase.
+use proptest::prelude::*;
+use proptest::collection::vec;
+use proptest::sample::{ Index, select };
+
+use std::collections::BTreeMap;
+
+use crate::roborally::state::{ Board, EConnection, EDirection, ETileType, MoveCard, MoveCardID, Player, Position, Register, RobotBuilder, State, TILE_TYPE_GLYPHS, parse_board, render_side_by_side };
+use super::register_engine::ESimpleMove;
+
+const MAX_SIZE: i32 = 6;
+const MAX_ROBOTS: usize = 4;
+const REGISTER_COUNT: usize = 5;
+
+/// A random board with random walls, split in two by a wall running from top to bottom, so there are at least two
+/// areas robots cannot move between
+#[derive(Debug, Clone)]
+pub struct SplitBoard {
+    pub board: Board,
+}
+
+#[derive(Debug, Clone)]
+pub struct RobotSpec {
+    /// Picks one of the tiles still free when the robot is placed
+    pub tile: Index,
+    pub direction: EDirection,
+    pub program: Vec<(Vec<ESimpleMove>, u32)>,
+}
+
+/// Portals are all generated as 'a' (see board_content)
+fn arb_glyph() -> impl Strategy<Value = char> {
+    let board_elements: Vec<char> = TILE_TYPE_GLYPHS.chars()
+        .filter(|c| *c != 'o' && *c != ' ' && !c.is_ascii_lowercase())
+        .collect();
+    prop_oneof![
+        6 => Just('o'),
+        1 => Just(' '),
+        4 => select(board_elements),
+        1 => Just('a'),
+    ]
+}
+
+fn arb_direction() -> impl Strategy<Value = EDirection> {
+    select(vec![EDirection::NORTH, EDirection::EAST, EDirection::SOUTH, EDirection::WEST])
+}
+
+fn arb_move() -> impl Strategy<Value = ESimpleMove> {
+    use ESimpleMove::*;
+    select(vec![Forward, Backward, StepLeft, StepRight, TurnRight, TurnLeft, UTurn])
+}
+
+pub fn arb_split_board() -> impl Strategy<Value = SplitBoard> {
+    (2..=MAX_SIZE, 2..=MAX_SIZE)
+        .prop_flat_map(|(size_x, size_y)| {
+            let tile_count = (size_x * size_y) as usize;
+            let wall_slot_count = ((size_x + 1) * (size_y * 2 + 1)) as usize;
+            (Just(size_x), Just(size_y), 1..size_x, vec(arb_glyph(), tile_count), vec(prop::bool::weighted(0.15), wall_slot_count))
+        })
+        .prop_map(|(size_x, size_y, split_x, glyphs, walls)| {
+            let content = board_content(size_x, size_y, split_x, &glyphs, &walls);
+            let board = parse_board(content.clone())
+                .unwrap_or_else(|err| panic!("Generated an invalid board:\n{}\n{}", content, err));
+            SplitBoard{ board }
+        })
+}
+
+/// Robots on random tiles, each with a program of REGISTER_COUNT cards
+pub fn arb_robots() -> impl Strategy<Value = Vec<RobotSpec>> {
+    let card = (vec(arb_move(), 0..=3), 0..100u32);
+    let robot = (any::<Index>(), arb_direction(), vec(card, REGISTER_COUNT))
+        .prop_map(|(tile, direction, program)| RobotSpec{ tile, direction, program });
+    vec(robot, 1..=MAX_ROBOTS)
+}
+
+/// Renders a board in .brd syntax: even rows hold the horizontal walls, odd rows alternate between vertical walls and tiles.
+/// Portals east of the split are labeled 'b', so no portal pair connects both sides.
+fn board_content(size_x: i32, size_y: i32, split_x: i32, glyphs: &[char], walls: &[bool]) -> String {
+    let width = (size_x * 2 + 1) as usize;
+    let mut rows = vec![];
+    for row in 0..(size_y * 2 + 1) as usize {
+        let mut line = String::new();
+        for column in 0..width {
+            let wall = walls[row * (size_x + 1) as usize + column / 2];
+            let c = match (row % 2, column % 2) {
+                (0, 1) if wall => '-',
+                (0, _) => ' ',
+                (_, 0) if wall || column as i32 == split_x * 2 => '|',
+                (_, 0) => ' ',
+                (_, _) => match glyphs[(row / 2) * size_x as usize + column / 2] {
+                    'a' if column as i32 > split_x * 2 => 'b',
+                    glyph => glyph,
+                },
+            };
+            line.push(c);
+        }
+        rows.push(line);
+    }
+    rows.join("\n")
+}
+
+/// Places the robots on free tiles (no pits). None if there is no room for all of them.
+pub fn create_state(split_board: &SplitBoard, robots: &[RobotSpec]) -> Option<Box<State>> {
+    let board = &split_board.board;
+    let mut free_positions: Vec<Position> = board.tiles.iter()
+        .filter(|t| t.ttype != ETileType::NoTile)
+        .map(|t| t.position)
+        .collect();
+    if free_positions.len() < robots.len() {
+        return None;
+    }
+
+    let mut players = vec![];
+    for (id, spec) in robots.iter().enumerate() {
+        let id = id as u32;
+        let position = free_positions.remove(spec.tile.index(free_positions.len()));
+        let robot = RobotBuilder::default()
+            .id(id)
+            .position(position)
+            .direction(spec.direction)
+            .build().unwrap();
+        let mut player = Player::new_with_move(id, robot, MoveCard::new_from_moves(0, 0, &[]));
+        // Priorities have to be unique within a register
+        player.registers = spec.program.iter()
+            .enumerate()
+            .map(|(register, (moves, priority))| Register {
+                move_card: Some(MoveCard::new_from_moves(1000 + id * 10 + register as u32, priority * 10 + id, moves)),
+                locked: false,
+            })
+            .collect();
+        let hand = vec![MoveCard::new_from_moves(2000 + id, 1, &[ESimpleMove::Forward])];
+        players.push(player.set_program_card_deck(hand));
+    }
+    Some(State::new_with_random_deck(board.clone(), players))
+}
+
+/// The invariants that have to hold after each register phase. Returns a description of the first violation.
+pub fn check_register_invariants(before: &State, after: &State) -> Result<(), String> {
+    let fail = |msg: String| Err(format!("{}\n{}", msg, render_side_by_side(before, after)));
+    let areas = walled_areas(&before.board);
+
+    // No two living (real) robots share a tile
+    let living: Vec<&Player> = after.active_players().filter(|p| !p.robot.is_virtual).collect();
+    for (i, a) in living.iter().enumerate() {
+        if let Some(b) = living[i + 1..].iter().find(|b| b.robot.position == a.robot.position) {
+            return fail(format!("Robots {} and {} share tile {:?}", a.id, b.id, a.robot.position));
+        }
+    }
+
+    for player in before.all_players() {
+        let robot_before = &player.robot;
+        let robot_after = match after.get_robot_by_player_id_or_fail(player.id) {
+            Ok(robot) => robot,
+            Err(err) => return fail(format!("{}", err)),
+        };
+        // Dead robots never move again
+        if robot_before.is_destroyed() {
+            if !robot_after.is_destroyed() || robot_after.position != robot_before.position {
+                return fail(format!("Dead robot {} moved", player.id));
+            }
+            continue;
+        }
+        // Robots never pass through walls: the living stay within their walled area
+        if !robot_after.is_destroyed() && areas.get(&robot_before.position) != areas.get(&robot_after.position) {
+            return fail(format!("Robot {} passed through a wall from {:?} to {:?}", player.id, robot_before.position, robot_after.position));
+        }
+    }
+
+    // Card counts are conserved
+    if all_card_ids(before) != all_card_ids(after) {
+        return fail(String::from("Cards changed"));
+    }
+    Ok(())
+}
+
+/// Numbers the areas of the board robots can move around in without passing through a wall. Portal partners belong
+/// to the same area.
+fn walled_areas(board: &Board) -> BTreeMap<Position, usize> {
+    let mut areas: BTreeMap<Position, usize> = BTreeMap::new();
+    let mut area_count = 0;
+    for tile in &board.tiles {
+        if areas.contains_key(&tile.position) {
+            continue;
+        }
+        let area = area_count;
+        area_count += 1;
+        let mut todo = vec![tile.position];
+        while let Some(position) = todo.pop() {
+            if areas.insert(position, area).is_some() {
+                continue;
+            }
+            let directions = [EDirection::NORTH, EDirection::EAST, EDirection::SOUTH, EDirection::WEST];
+            let neighbors = directions.iter().filter_map(|d| match board.get_neighbor_in(&position, *d) {
+                Ok(EConnection::Free(neighbor)) => Some(neighbor),
+                _ => None,
+            });
+            todo.extend(neighbors.chain(board.get_portal_partner(&position)).filter(|p| !areas.contains_key(p)));
+        }
+    }
+    areas
+}
+
+fn all_card_ids(state: &State) -> Vec<MoveCardID> {
+    let mut ids: Vec<MoveCardID> = state.deck.cards.iter().map(|c| c.id).collect();
+    for player in state.all_players() {
+        ids.extend(player.program_card_deck.iter().map(|c| c.id));
+        ids.extend(player.registers.iter().filter_map(|r| r.move_card.as_ref()).map(|c| c.id));
+    }
+    ids.sort_unstable();
+    ids
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    use crate::roborally::engine::register_engine::RegisterEngine;
+    use super::{ arb_robots, arb_split_board, check_register_invariants, create_state };
+
+    proptest! {
+        #[test]
+        fn test_register_invariants(split_board in arb_split_board(), robots in arb_robots()) {
+            let state = create_state(&split_board, &robots);
+            prop_assume!(state.is_some());
+            let mut state = state.unwrap();
+
+            let engine = RegisterEngine::new();
+            for register_index in 0..state.register_count() {
+                let next = engine.run_register_phase(state.clone(), register_index)
+                    .map_err(|err| TestCaseError::fail(format!("Register {}: {}\n{}", register_index, err, state)))?;
+                check_register_invariants(&state, &next)
+                    .map_err(|msg| TestCaseError::fail(format!("Register {}: {}", register_index, msg)))?;
+                state = next;
+            }
+        }
+    }
+}
\ No newline at end of file
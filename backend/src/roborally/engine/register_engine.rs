@@ -1,11 +1,11 @@
 #![deny(clippy::single_match)]
 use std::slice::Iter;
 use std::fmt;
-use std::collections::HashSet;
 
 use failure::Fail;
 
-use crate::roborally::state::{EConnection, EDirection, ERotationDirection, ETileType, PlayerID, Position, RobotID, State, StateError};
+use crate::roborally::state::{EConnection, EDirection, ETileType, MoveCard, PlayerID, Position, RobotID, State, StateError};
+use super::conveyor_resolver::{ ConveyorMove, arrival_rotation, resolve_conveyor_moves };
 
 #[derive(Debug, Fail)]
 pub enum RegisterEngineError {
@@ -15,6 +15,7 @@ pub enum RegisterEngineError {
     },
     #[fail(display = "State error: {}", err)]
     StateError {
+        #[cause]
         err: StateError,
     }
 }
@@ -42,55 +43,70 @@ impl RegisterEngine {
         Ok(state)
     }
 
-    fn run_register_phase(&self, state: Box<State>, register_index: usize) -> Result<Box<State>, RegisterEngineError> {
-        let mut state = state;
+    /// Runs a single register phase in which only the given player plays a card, the other robots either are moved by
+    /// board elements (Idle) or stay where they are unless pushed (Static). Used to preview programs.
+    pub fn simulate_register_phase(&self, state: Box<State>, register_index: usize, player_id: PlayerID, card: &MoveCard, mode: ESimulationMode) -> Result<Box<State>, RegisterEngineError> {
+        let state = self.perform_card_moves(state, vec![(player_id, card.clone())])?;
+        let board_moved_player_ids = mode.board_moved_player_ids(&state, player_id);
+        self.perform_board_element_moves(state, register_index, &board_moved_player_ids)
+    }
 
-        // 1. Robots move, in order of Priority
+    /// Runs the given register of all players, followed by the board elements
+    pub fn run_register_phase(&self, state: Box<State>, register_index: usize) -> Result<Box<State>, RegisterEngineError> {
         let player_move_cards = state.get_register_cards_sorted_by_priority(register_index)?;
+        let state = self.perform_card_moves(state, player_move_cards)?;
+        let active_player_ids = state.active_player_ids();
+        self.perform_board_element_moves(state, register_index, &active_player_ids)
+    }
+
+    /// 1. Robots move, in order of Priority
+    pub fn perform_card_moves(&self, state: Box<State>, player_move_cards: Vec<(PlayerID, MoveCard)>) -> Result<Box<State>, RegisterEngineError> {
+        let mut state = state;
         for player_card in player_move_cards {
             let tmove = player_card.1.tmove;
+            let before = state.clone();
             state = self.perform_move(state, player_card.0, tmove)?;
+            state = self.perform_teleports(&before, state)?;
         }
+        Ok(state)
+    }
+
+    /// 2. - 4. Board elements move the robots of the given players (if still active)
+    pub fn perform_board_element_moves(&self, state: Box<State>, register_index: usize, player_ids: &[PlayerID]) -> Result<Box<State>, RegisterEngineError> {
+        let mut state = state;
 
         // 2. Board elements move:
         // a. express conveyor belt move 1
-        state = self.perform_conveyor_move(state, true)?;
+        let before = state.clone();
+        state = self.perform_conveyor_move(state, player_ids, true)?;
+        state = self.perform_teleports(&before, state)?;
 
         // b. Express conveyor belt and normal conveyor belts move 1 space
-        state = self.perform_conveyor_move(state, false)?;
+        let before = state.clone();
+        state = self.perform_conveyor_move(state, player_ids, false)?;
+        state = self.perform_teleports(&before, state)?;
 
         // c. Pusher: push if active (depends on phase)
 
         // d. Gears rotate
-        state = self.perform_rotations(state)?;
+        state = self.perform_rotations(state, player_ids)?;
 
-        // 3. Board and robot lasers fire
+        // e. Crushers crush (depends on register)
+        state = self.perform_crushers(state, register_index, player_ids)?;
+
+        // 3. Board and robot lasers fire (virtual robots neither shoot nor get shot)
         // 4. Robots on flags or repair site: update archive markers
 
-        Ok(state)
+        // 5. Virtual robots that are alone on their tile become real
+        Ok(state.make_lone_virtual_robots_real())
     }
 
-    fn perform_conveyor_move(&self, state: Box<State>, express_only: bool) -> Result<Box<State>, RegisterEngineError> {
-        //  1. gather potential move targets. Don't move through obstacles (any other than walls?)
+    fn perform_conveyor_move(&self, state: Box<State>, player_ids: &[PlayerID], express_only: bool) -> Result<Box<State>, RegisterEngineError> {
+        //  1. gather potential move targets
         let mut state = state;
-
-        //  2 weed out duplicates (no two robots may move onto the same tile) "ghosting" (don't move through each other)
-        //     Generally: "If it's not clear what you should do, don't move either robot.")
-        #[derive(Clone, Copy)]
-        struct Move {
-            robot_id: RobotID,
-            target_position: Position,
-            origin_position: Position,
-            outbound_direction: EDirection,
-            connection: EConnection,
-        }
-        impl Move {
-            fn new(robot_id: RobotID, target_position: Position, origin_position: Position, outbound_direction: EDirection, connection: EConnection) -> Self {
-                Move { robot_id, target_position, origin_position, outbound_direction, connection }
-            }
-        }
-        let mut moves: Vec<Move> = vec![];
-        for player_id in state.active_player_ids() {
+        let mut moves: Vec<ConveyorMove> = vec![];
+        let mut virtual_moves: Vec<ConveyorMove> = vec![];
+        for player_id in active_player_ids_of(&state, player_ids) {
             let robot = state.get_robot_by_player_id_or_fail(player_id)?;
             let tile_type = state.board.get_tile_type_at(&robot.position)?;
             let outbound_direction = match tile_type {
@@ -109,48 +125,28 @@ impl RegisterEngine {
                 _ => continue,
             };
             let connection = state.board.get_neighbor_in(&robot.position, outbound_direction)?;
-            match connection {
-                EConnection::Walled => moves.push(Move::new(robot.id, robot.position, robot.position, outbound_direction, connection)),
-                EConnection::OffPlatform(to) => moves.push(Move::new(robot.id, to, robot.position, outbound_direction, connection)),
-                EConnection::Free(to) => moves.push(Move::new(robot.id, to, robot.position, outbound_direction, connection)),
-            };
-        }
-        
-        // 2.1 duplicate targets: both don't move
-        let mut moves = {
-            moves.sort_by(|a, b| a.target_position.partial_cmp(&b.target_position).unwrap());
-
-            let mut i: usize = 0;
-            let mut i_out: usize = 0;
-            let max = moves.len() as i32 - 1;
-            while (i as i32) <= max {
-                if let Some(next_move) = moves.get(i + 1) {
-                    if moves[i].target_position.eq(&next_move.target_position) {
-                        // two robots try to move to the same position: don't move either
-                        i = i + 2;
-                        continue;
-                    }
-                }
-                moves[i_out] = moves[i];
-                i = i + 1;
-                i_out = i_out + 1;
-            }
-            let (moves_new, _) = moves.split_at(i_out as usize);
-            moves_new.to_vec()
-        };
-        
-        // 2.2 ghosting (both exchange position): don't move, either.
-        let mut to_remove: HashSet<RobotID> = HashSet::new();
-        for mov in moves.iter() {
-            if moves.iter().any(|m| m.target_position == mov.origin_position && m.origin_position == mov.target_position) {
-                to_remove.insert(mov.robot_id);
+            let mov = ConveyorMove::new(robot.id, robot.position, outbound_direction, connection);
+            if robot.is_virtual {
+                virtual_moves.push(mov);
+            } else {
+                moves.push(mov);
             }
         }
-        moves.retain(|mov| to_remove.get(&mov.robot_id).is_none());
+
+        //  2. weed out moves that are blocked, conflicting or ghosting
+        //     Generally: "If it's not clear what you should do, don't move either robot."
+        //     Virtual robots neither block nor get blocked: only walls stop them.
+        let robot_positions: Vec<Position> = state.active_players()
+            .filter(|p| !p.robot.is_virtual)
+            .map(|p| p.robot.position)
+            .collect();
+        let mut moves = resolve_conveyor_moves(moves, &robot_positions);
+        moves.extend(virtual_moves.into_iter()
+            .filter(|m| m.connection != EConnection::Walled));
 
         //  3. Move all robots at once.
         for mov in moves {
-            let Move{ robot_id: id, outbound_direction, connection, ..  } = mov;
+            let ConveyorMove{ robot_id: id, outbound_direction, connection, ..  } = mov;
             let robot = state.get_robot_by_id_or_fail(id)?;
             let new_robot = match connection {
                 EConnection::Walled => continue,
@@ -160,42 +156,7 @@ impl RegisterEngine {
                         .die()
                 },
                 EConnection::Free(to) => {
-                    // Check: Don't move us into a static robot!
-                    // TODO (geropl): It feels odd to check this here. Should this be pushed to Board?
-                    //                In general, we try to be a tad to smart here. Alternatively, we could just move everything
-                    //                temporarily, observe the result and weed out wrong results by eliminating moves.
-                    if let Some(_) = state.get_robot_at_position(&to) {
-                        let is_static = match state.board.get_tile_type_at(&to)? {
-                            ETileType::Conveyor2{..} => false,
-                            ETileType::Conveyor3{..} => false,
-                            _ => true,
-                        };
-                        if is_static {
-                            continue;
-                        }
-                    }
-
-                    let inbound_direction = outbound_direction.turn_around();
-                    let rotation_direction = match state.board.get_tile_type_at(&to)? {
-                        ETileType::Conveyor2{ out, input, .. } => {
-                            if input == inbound_direction {
-                                outbound_direction.try_rotate_towards(&out)
-                            } else {
-                                None
-                            }
-                        },
-                        ETileType::Conveyor3{ out, inputs, .. } => {
-                            let mut rotate: Option<ERotationDirection> = None;
-                            for input in &inputs {
-                                if *input == inbound_direction {
-                                    rotate = outbound_direction.try_rotate_towards(&out);
-                                    break;
-                                }
-                            }
-                            rotate
-                        }
-                        _ => None,
-                    };
+                    let rotation_direction = arrival_rotation(state.board.get_tile_type_at(&to)?, outbound_direction);
                     let mut new_robot = robot.set_position(to);
                     if let Some(rotation_direction) = rotation_direction {
                         new_robot = new_robot.set_direction(robot.direction.rotate(&rotation_direction));
@@ -209,10 +170,10 @@ impl RegisterEngine {
         Ok(state)
     }
 
-    fn perform_rotations(&self, state: Box<State>) -> Result<Box<State>, RegisterEngineError> {
+    fn perform_rotations(&self, state: Box<State>, player_ids: &[PlayerID]) -> Result<Box<State>, RegisterEngineError> {
         let mut state = state;
 
-        for player_id in state.active_player_ids() {
+        for player_id in active_player_ids_of(&state, player_ids) {
             let robot = state.get_robot_by_player_id_or_fail(player_id)?;
             let tile_type = state.board.get_tile_type_at(&robot.position)?;
             if let ETileType::Rotator { dir } = tile_type {
@@ -223,6 +184,44 @@ impl RegisterEngine {
         Ok(state)
     }
 
+    /// Robots that ended a move on a portal (i.e., moved since before) are moved to its partner, if no robot stands
+    /// there. Walls around the partner don't matter. Robots arriving through a portal didn't move onto it, so
+    /// teleporting does not chain.
+    fn perform_teleports(&self, before: &State, state: Box<State>) -> Result<Box<State>, RegisterEngineError> {
+        let mut teleports = vec![];
+        for player in state.active_players() {
+            let robot = &player.robot;
+            if before.get_robot_by_player_id_or_fail(player.id)?.position == robot.position {
+                continue;
+            }
+            if let Some(partner) = state.board.get_portal_partner(&robot.position) {
+                if state.find_obstructing_robot(robot, &partner).is_none() {
+                    teleports.push(robot.set_position(partner));
+                }
+            }
+        }
+
+        let mut state = state;
+        for robot in teleports {
+            state = state.update_robot(robot)?;
+        }
+        Ok(state)
+    }
+
+    fn perform_crushers(&self, state: Box<State>, register_index: usize, player_ids: &[PlayerID]) -> Result<Box<State>, RegisterEngineError> {
+        let mut state = state;
+
+        for player_id in active_player_ids_of(&state, player_ids) {
+            let robot = state.get_robot_by_player_id_or_fail(player_id)?;
+            if let ETileType::Crusher { registers } = state.board.get_tile_type_at(&robot.position)? {
+                if registers.contains(register_index) {
+                    state = state.update_robot(robot.die())?;
+                }
+            }
+        }
+        Ok(state)
+    }
+
     fn perform_move(&self, state: Box<State>, player_id: PlayerID, tmove: Box<dyn TMove>) -> Result<Box<State>, RegisterEngineError> {
         let mut state = state;
         for smove in tmove.iter() {
@@ -283,7 +282,7 @@ impl RegisterEngine {
             };
 
             // Watch out for the next robot for our chain
-            let robot_in_my_way = state.find_robot_at(&to);
+            let robot_in_my_way = state.find_obstructing_robot(robot, &to);
             if robot_in_my_way.is_none() {
                 // Chain discontinued: End here
                 break;
@@ -295,6 +294,7 @@ impl RegisterEngine {
         }
 
         // 2. Try to actually move
+        let chain = push_stack.clone();
         while !push_stack.is_empty() {
             let robot_id = push_stack.last().unwrap();  // unwrap ok because we check is_empty before
             let robot = state.get_robot_by_id_or_fail(*robot_id)?;
@@ -318,9 +318,41 @@ impl RegisterEngine {
             state = state.update_robot(new_robot)?;
             push_stack.pop();
         }
+
+        // 3. Robots on slippery tiles slide on, the front one first to make room
+        for robot_id in chain.iter().rev() {
+            state = self.slide_robot(state, *robot_id, direction)?;
+        }
         Ok(state)
     }
 
+    /// Moves the robot on in direction as long as it is on a slippery tile, until it hits a wall, a robot or a
+    /// non-slippery tile (or falls off the platform)
+    fn slide_robot(&self, state: Box<State>, robot_id: RobotID, direction: EDirection) -> Result<Box<State>, RegisterEngineError> {
+        let mut state = state;
+        loop {
+            let robot = state.get_robot_by_id_or_fail(robot_id)?;
+            if robot.is_destroyed() || state.board.get_tile_type_at(&robot.position)? != ETileType::Slippery {
+                return Ok(state);
+            }
+
+            let new_robot = match state.board.get_neighbor_in(&robot.position, direction)? {
+                EConnection::Free(to) => {
+                    if state.find_obstructing_robot(robot, &to).is_some() {
+                        return Ok(state);
+                    }
+                    robot.set_position(to)
+                },
+                EConnection::Walled => return Ok(state),
+                EConnection::OffPlatform(to) => {
+                    robot.set_position(to)
+                        .die()
+                },
+            };
+            state = state.update_robot(new_robot)?;
+        }
+    }
+
     fn map_move_to_direction_change(smove: &ESimpleMove, dir: EDirection) -> EDirection {
         match smove {
             // Turn
@@ -337,7 +369,31 @@ impl RegisterEngine {
     }
 }
 
-#[derive(Debug, Clone)]
+fn active_player_ids_of(state: &State, player_ids: &[PlayerID]) -> Vec<PlayerID> {
+    state.active_player_ids().into_iter()
+        .filter(|id| player_ids.contains(id))
+        .collect()
+}
+
+/// How robots of other players behave when simulating a single player's program
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ESimulationMode {
+    /// Other robots don't move at all (but may be pushed)
+    Static,
+    /// Other robots play no cards, but are moved by board elements
+    Idle,
+}
+
+impl ESimulationMode {
+    pub fn board_moved_player_ids(self, state: &State, player_id: PlayerID) -> Vec<PlayerID> {
+        match self {
+            ESimulationMode::Static => vec![player_id],
+            ESimulationMode::Idle => state.active_player_ids(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ESimpleMove {
     Forward,
     Backward,
@@ -390,6 +446,14 @@ mod test {
     use crate::roborally::state::*;
     use crate::roborally::engine::register_engine::*;
 
+    /// Runs all registers and renders the states before and after, for assertion messages
+    fn execute_registers(state: Box<State>) -> Result<(Box<State>, String), Error> {
+        let before = state.clone();
+        let after = RegisterEngine::default().execute_registers(state)?;
+        let diff = render_side_by_side(&before, &after);
+        Ok((after, diff))
+    }
+
     fn create_state(board_name: Option<&'static str>) -> Result<(Board, Vec<Player>), Error> {
         // State
         let robot1 = RobotBuilder::default()
@@ -415,15 +479,14 @@ mod test {
         let (board, players) = create_state(None)?;
         let state = State::new_with_random_deck(board, players);
         
-        let engine = RegisterEngine::default();
-        let actual_state = engine.execute_registers(state)?;
+        let (actual_state, diff) = execute_registers(state)?;
 
         let actual_robot1 = actual_state.get_robot_by_player_id_or_fail(0)?;
         let actual_robot2 = actual_state.get_robot_by_player_id_or_fail(1)?;
-        assert_eq!(actual_robot1.direction, EDirection::NORTH, "robot1 direction");
-        assert_eq!(actual_robot1.position, Position { x: 2, y: 1 }, "robot1 position");
-        assert_eq!(actual_robot2.direction, EDirection::NORTH, "robot2 direction");
-        assert_eq!(actual_robot2.position, Position { x: 4, y: 3 }, "robot2 position");
+        assert_eq!(actual_robot1.direction, EDirection::NORTH, "robot1 direction\n{}", diff);
+        assert_eq!(actual_robot1.position, Position { x: 2, y: 1 }, "robot1 position\n{}", diff);
+        assert_eq!(actual_robot2.direction, EDirection::NORTH, "robot2 direction\n{}", diff);
+        assert_eq!(actual_robot2.position, Position { x: 4, y: 3 }, "robot2 position\n{}", diff);
 
         Ok(())
     }
@@ -455,15 +518,14 @@ mod test {
         // State
         let state = State::new_with_random_deck(board, players);
         
-        let engine = RegisterEngine::default();
-        let actual_state = engine.execute_registers(state)?;
+        let (actual_state, diff) = execute_registers(state)?;
 
         let actual_robot1 = actual_state.get_robot_by_player_id_or_fail(0)?;
         let actual_robot2 = actual_state.get_robot_by_player_id_or_fail(1)?;
-        assert_eq!(actual_robot1.direction, EDirection::NORTH, "robot1 direction");
-        assert_eq!(actual_robot1.position, Position { x: 0, y: -1 }, "robot1 position");
-        assert_eq!(actual_robot2.direction, EDirection::SOUTH, "robot2 direction");
-        assert_eq!(actual_robot2.position, Position { x: 1, y: 1 }, "robot2 position");
+        assert_eq!(actual_robot1.direction, EDirection::NORTH, "robot1 direction\n{}", diff);
+        assert_eq!(actual_robot1.position, Position { x: 0, y: -1 }, "robot1 position\n{}", diff);
+        assert_eq!(actual_robot2.direction, EDirection::SOUTH, "robot2 direction\n{}", diff);
+        assert_eq!(actual_robot2.position, Position { x: 1, y: 1 }, "robot2 position\n{}", diff);
 
         Ok(())
     }
@@ -495,15 +557,14 @@ mod test {
         // State
         let state = State::new_with_random_deck(board, players);
         
-        let engine = RegisterEngine::default();
-        let actual_state = engine.execute_registers(state)?;
+        let (actual_state, diff) = execute_registers(state)?;
 
         let actual_robot1 = actual_state.get_robot_by_player_id_or_fail(0)?;
         let actual_robot2 = actual_state.get_robot_by_player_id_or_fail(1)?;
-        assert_eq!(actual_robot1.position, Position { x: 2, y: 1 }, "robot1 position");
-        assert_eq!(actual_robot1.direction, EDirection::WEST, "robot1 direction");
-        assert_eq!(actual_robot2.direction, EDirection::SOUTH, "robot2 direction");
-        assert_eq!(actual_robot2.position, Position { x: 0, y: 1 }, "robot2 position");
+        assert_eq!(actual_robot1.position, Position { x: 2, y: 1 }, "robot1 position\n{}", diff);
+        assert_eq!(actual_robot1.direction, EDirection::WEST, "robot1 direction\n{}", diff);
+        assert_eq!(actual_robot2.direction, EDirection::SOUTH, "robot2 direction\n{}", diff);
+        assert_eq!(actual_robot2.position, Position { x: 0, y: 1 }, "robot2 position\n{}", diff);
 
         Ok(())
     }
@@ -526,12 +587,11 @@ mod test {
         // State
         let state = State::new_with_random_deck(board, players);
         
-        let engine = RegisterEngine::default();
-        let actual_state = engine.execute_registers(state)?;
+        let (actual_state, diff) = execute_registers(state)?;
 
         let actual_robot1 = actual_state.get_robot_by_player_id_or_fail(0)?;
-        assert_eq!(actual_robot1.position, Position { x: 1, y: 3 }, "robot1 position");
-        assert_eq!(actual_robot1.direction, EDirection::SOUTH, "robot1 direction");
+        assert_eq!(actual_robot1.position, Position { x: 1, y: 3 }, "robot1 position\n{}", diff);
+        assert_eq!(actual_robot1.direction, EDirection::SOUTH, "robot1 direction\n{}", diff);
 
         Ok(())
     }
@@ -554,12 +614,11 @@ mod test {
         // State
         let state = State::new_with_random_deck(board, players);
         
-        let engine = RegisterEngine::default();
-        let actual_state = engine.execute_registers(state)?;
+        let (actual_state, diff) = execute_registers(state)?;
 
         let actual_robot1 = actual_state.get_robot_by_player_id_or_fail(0)?;
-        assert_eq!(actual_robot1.position, Position { x: 1, y: 4 }, "robot1 position");
-        assert_eq!(actual_robot1.direction, EDirection::EAST, "robot1 direction");
+        assert_eq!(actual_robot1.position, Position { x: 1, y: 4 }, "robot1 position\n{}", diff);
+        assert_eq!(actual_robot1.direction, EDirection::EAST, "robot1 direction\n{}", diff);
 
         Ok(())
     }
@@ -591,15 +650,14 @@ mod test {
         // State
         let state = State::new_with_random_deck(board, players);
         
-        let engine = RegisterEngine::default();
-        let actual_state = engine.execute_registers(state)?;
+        let (actual_state, diff) = execute_registers(state)?;
 
         let actual_robot1 = actual_state.get_robot_by_player_id_or_fail(0)?;
         let actual_robot2 = actual_state.get_robot_by_player_id_or_fail(1)?;
-        assert_eq!(actual_robot1.position, Position { x: 0, y: 0 }, "robot1 position");
-        assert_eq!(actual_robot1.direction, EDirection::WEST, "robot1 direction");
-        assert_eq!(actual_robot2.direction, EDirection::WEST, "robot2 direction");
-        assert_eq!(actual_robot2.position, Position { x: 0, y: 1 }, "robot2 position");
+        assert_eq!(actual_robot1.position, Position { x: 0, y: 0 }, "robot1 position\n{}", diff);
+        assert_eq!(actual_robot1.direction, EDirection::WEST, "robot1 direction\n{}", diff);
+        assert_eq!(actual_robot2.direction, EDirection::WEST, "robot2 direction\n{}", diff);
+        assert_eq!(actual_robot2.position, Position { x: 0, y: 1 }, "robot2 position\n{}", diff);
 
         Ok(())
     }
@@ -631,15 +689,14 @@ mod test {
         // State
         let state = State::new_with_random_deck(board, players);
         
-        let engine = RegisterEngine::default();
-        let actual_state = engine.execute_registers(state)?;
+        let (actual_state, diff) = execute_registers(state)?;
 
         let actual_robot1 = actual_state.get_robot_by_player_id_or_fail(0)?;
         let actual_robot2 = actual_state.get_robot_by_player_id_or_fail(1)?;
-        assert_eq!(actual_robot1.position, Position { x: 2, y: 1 }, "robot1 position");
-        assert_eq!(actual_robot1.direction, EDirection::SOUTH, "robot1 direction");
-        assert_eq!(actual_robot2.direction, EDirection::NORTH, "robot2 direction");
-        assert_eq!(actual_robot2.position, Position { x: 3, y: 1 }, "robot2 position");
+        assert_eq!(actual_robot1.position, Position { x: 2, y: 1 }, "robot1 position\n{}", diff);
+        assert_eq!(actual_robot1.direction, EDirection::SOUTH, "robot1 direction\n{}", diff);
+        assert_eq!(actual_robot2.direction, EDirection::NORTH, "robot2 direction\n{}", diff);
+        assert_eq!(actual_robot2.position, Position { x: 3, y: 1 }, "robot2 position\n{}", diff);
 
         Ok(())
     }
@@ -671,15 +728,14 @@ mod test {
         // State
         let state = State::new_with_random_deck(board, players);
         
-        let engine = RegisterEngine::default();
-        let actual_state = engine.execute_registers(state)?;
+        let (actual_state, diff) = execute_registers(state)?;
 
         let actual_robot1 = actual_state.get_robot_by_player_id_or_fail(0)?;
         let actual_robot2 = actual_state.get_robot_by_player_id_or_fail(1)?;
-        assert_eq!(actual_robot1.position, Position { x: 2, y: 0 }, "robot1 position");
-        assert_eq!(actual_robot1.direction, EDirection::SOUTH, "robot1 direction");
-        assert_eq!(actual_robot2.direction, EDirection::NORTH, "robot2 direction");
-        assert_eq!(actual_robot2.position, Position { x: 3, y: 0 }, "robot2 position");
+        assert_eq!(actual_robot1.position, Position { x: 2, y: 0 }, "robot1 position\n{}", diff);
+        assert_eq!(actual_robot1.direction, EDirection::SOUTH, "robot1 direction\n{}", diff);
+        assert_eq!(actual_robot2.direction, EDirection::NORTH, "robot2 direction\n{}", diff);
+        assert_eq!(actual_robot2.position, Position { x: 3, y: 0 }, "robot2 position\n{}", diff);
 
         Ok(())
     }
@@ -711,17 +767,89 @@ mod test {
         // State
         let state = State::new_with_random_deck(board, players);
         
-        let engine = RegisterEngine::default();
-        let actual_state = engine.execute_registers(state)?;
+        let (actual_state, diff) = execute_registers(state)?;
 
         let actual_robot1 = actual_state.get_robot_by_player_id_or_fail(0)?;
         let actual_robot2 = actual_state.get_robot_by_player_id_or_fail(1)?;
         
-        assert_eq!(actual_robot1.is_destroyed(), true, "robot1 is_destroyed");
-        assert_eq!(actual_robot1.direction, EDirection::EAST, "robot1 direction");
-        assert_eq!(actual_robot2.direction, EDirection::EAST, "robot2 direction");
-        assert_eq!(actual_robot2.is_destroyed(), true, "robot2 is_destroyed");
+        assert_eq!(actual_robot1.is_destroyed(), true, "robot1 is_destroyed\n{}", diff);
+        assert_eq!(actual_robot1.direction, EDirection::EAST, "robot1 direction\n{}", diff);
+        assert_eq!(actual_robot2.direction, EDirection::EAST, "robot2 direction\n{}", diff);
+        assert_eq!(actual_robot2.is_destroyed(), true, "robot2 is_destroyed\n{}", diff);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_conveyor_rotation_matrix() -> Result<(), Error> {
+        use EDirection::*;
+        use ESimpleMove::*;
+        // (description, start position, start direction, card, expected position, expected direction)
+        type Case<'a> = (&'a str, Position, EDirection, &'a [ESimpleMove], Position, EDirection);
+        let cases: &[Case] = &[
+            ("express belt onto curve rotates in the first pass", Position::new(0, 3), NORTH, &[], Position::new(1, 4), WEST),
+            ("card move onto express belt, then onto curve", Position::new(0, 2), SOUTH, &[Forward], Position::new(1, 4), EAST),
+            ("card move onto curve does not rotate", Position::new(1, 4), WEST, &[Forward], Position::new(1, 4), WEST),
+            ("leaving a curve does not rotate", Position::new(0, 4), NORTH, &[], Position::new(1, 4), NORTH),
+            ("entering a belt from the side does not rotate", Position::new(1, 3), NORTH, &[], Position::new(0, 3), NORTH),
+            ("entering a belt against its direction does not rotate", Position::new(2, 1), NORTH, &[], Position::new(3, 1), NORTH),
+            ("straight express belt", Position::new(3, 3), EAST, &[], Position::new(1, 3), EAST),
+            ("normal belt does not move in the express pass", Position::new(4, 3), SOUTH, &[], Position::new(3, 3), SOUTH),
+            ("card move off a belt onto a gear only rotates once", Position::new(4, 3), NORTH, &[Forward], Position::new(4, 2), EAST),
+            ("card move onto a gear rotates", Position::new(3, 2), EAST, &[Forward], Position::new(4, 2), SOUTH),
+        ];
+        for (description, position, direction, moves, expected_position, expected_direction) in cases {
+            let robot = RobotBuilder::default()
+                .id(0)
+                .position(*position)
+                .direction(*direction)
+                .build().unwrap();
+            let player = Player::new_with_move(0, robot, MoveCard::new_from_moves(0, 1, moves));
+            let state = State::new_with_random_deck(Board::load_board_by_name("test-conveyor-moves")?, vec![player]);
+
+            let (actual_state, diff) = execute_registers(state)?;
+
+            let actual_robot = actual_state.get_robot_by_player_id_or_fail(0)?;
+            assert_eq!(actual_robot.position, *expected_position, "{}: position\n{}", description, diff);
+            assert_eq!(actual_robot.direction, *expected_direction, "{}: direction\n{}", description, diff);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_crushers() -> Result<(), Error> {
+        // Crushers active in all, odd (1, 3, 5) and even (2, 4) registers
+        let board = parse_board([
+            "       ",
+            " ▣ ◧ ◨ ",
+            "       ",
+            " o o o ",
+            "       ",
+        ].join("\n"))?;
+        let players: Vec<Player> = (0..3)
+            .map(|id| {
+                let robot = RobotBuilder::default()
+                    .id(id)
+                    .position(Position::new(id as i32, 1))
+                    .direction(EDirection::NORTH)
+                    .build().unwrap();
+                let mut player = Player::new_with_move(id, robot, MoveCard::new_from_moves(id, id + 1, &[ESimpleMove::Forward]));
+                player.registers.push(Register{ move_card: Some(MoveCard::new_from_moves(10 + id, id + 1, &[])), locked: false });
+                player
+            })
+            .collect();
+        let engine = RegisterEngine::new();
+        let state = State::new_with_random_deck(board, players);
+
+        let state = engine.run_register_phase(state, 0)?;
+        assert!(state.get_robot_by_player_id_or_fail(0)?.is_destroyed(), "all registers: crushed in register 1\n{}", state);
+        assert!(state.get_robot_by_player_id_or_fail(1)?.is_destroyed(), "odd registers: crushed in register 1\n{}", state);
+        assert!(!state.get_robot_by_player_id_or_fail(2)?.is_destroyed(), "even registers: not crushed in register 1\n{}", state);
 
+        let state = engine.run_register_phase(state, 1)?;
+        let robot2 = state.get_robot_by_player_id_or_fail(2)?;
+        assert!(robot2.is_destroyed(), "even registers: crushed in register 2\n{}", state);
+        assert_eq!(robot2.position, Position::new(2, 0), "crushed where it stands\n{}", state);
         Ok(())
     }
 
@@ -782,15 +910,14 @@ mod test {
         // State
         let state = State::new_with_random_deck(board, players);
         
-        let engine = RegisterEngine::default();
-        let actual_state = engine.execute_registers(state)?;
+        let (actual_state, diff) = execute_registers(state)?;
 
         let actual_robot1 = actual_state.get_robot_by_player_id_or_fail(0)?;
         let actual_robot2 = actual_state.get_robot_by_player_id_or_fail(1)?;
-        assert_eq!(actual_robot1.direction, EDirection::NORTH, "robot1 direction");
-        assert_eq!(actual_robot1.position, robot1_pos, "robot1 position");
-        assert_eq!(actual_robot2.direction, EDirection::WEST, "robot2 direction");
-        assert_eq!(actual_robot2.position, robot2_pos, "robot2 position");
+        assert_eq!(actual_robot1.direction, EDirection::NORTH, "robot1 direction\n{}", diff);
+        assert_eq!(actual_robot1.position, robot1_pos, "robot1 position\n{}", diff);
+        assert_eq!(actual_robot2.direction, EDirection::WEST, "robot2 direction\n{}", diff);
+        assert_eq!(actual_robot2.position, robot2_pos, "robot2 position\n{}", diff);
 
         Ok(())
     }
@@ -0,0 +1,424 @@
+//! Declarative rules regression tests. A scenario file has four sections:
+//!
+//! ```text
+//! # A robot pushes another one off the board
+//! board:
+//!
+//!  A B o
+//!
+//! robots:
+//! A EAST
+//! B WEST on →
+//! program:
+//! A: Forward Forward | TurnLeft
+//! expect:
+//! A: 2 0 EAST
+//! B: destroyed
+//! ```
+//!
+//! - board: a snippet in .brd syntax. Robots are placed inline as letters (A is player 0, B player 1, ...) and stand on
+//!   a regular tile unless the robots section says otherwise. Trailing whitespace does not matter.
+//! - robots: `<letter> <direction> [at <x>,<y>] [on <glyph>] [damage <n>] [virtual]`, robots not listed face NORTH.
+//!   `at` moves a robot away from its letter, e.g. onto the tile of another robot it shares as a virtual robot.
+//! - program: `<letter>: <register> | <register> | ...` where a register is a sequence of simple moves (`-` for none),
+//!   optionally followed by `@<priority>`. By default, players listed first move first.
+//! - expect: `<letter>: <x> <y> <direction> [damage <n>] [virtual|real]` or `<letter>: destroyed`. Robots not listed
+//!   are not checked.
+use failure::{ Error, Fail };
+
+use crate::roborally::state::{ Board, EDirection, MoveCard, Player, PlayerID, Position, Register, RobotBuilder, State, parse_board, render_side_by_side };
+use super::register_engine::{ ESimpleMove, RegisterEngine };
+
+#[derive(Debug, Fail)]
+pub enum ScenarioError {
+    #[fail(display = "line {}: {}", line, msg)]
+    SyntaxError {
+        line: usize,
+        msg: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum EExpectation {
+    At {
+        position: Position,
+        direction: EDirection,
+        damage: Option<u32>,
+        is_virtual: Option<bool>,
+    },
+    Destroyed,
+}
+
+#[derive(Debug)]
+struct ScenarioRobot {
+    player_id: PlayerID,
+    position: Position,
+    direction: EDirection,
+    damage: u32,
+    is_virtual: bool,
+}
+
+#[derive(Debug)]
+pub struct Scenario {
+    pub description: String,
+    board: Board,
+    robots: Vec<ScenarioRobot>,
+    programs: Vec<(PlayerID, Vec<MoveCard>)>,
+    expectations: Vec<(PlayerID, EExpectation)>,
+}
+
+#[derive(PartialEq)]
+enum ESection {
+    Description,
+    Board,
+    Robots,
+    Program,
+    Expect,
+}
+
+impl Scenario {
+    pub fn parse(content: &str) -> Result<Scenario, Error> {
+        let mut description = vec![];
+        let mut board_lines: Vec<&str> = vec![];
+        let mut robot_lines = vec![];
+        let mut program_lines = vec![];
+        let mut expect_lines = vec![];
+
+        let mut section = ESection::Description;
+        for (index, line) in content.lines().enumerate() {
+            let number = index + 1;
+            if let Some(comment) = line.strip_prefix('#') {
+                if section == ESection::Description {
+                    description.push(comment.trim());
+                }
+                continue;
+            }
+            section = match line.trim_end() {
+                "board:" => ESection::Board,
+                "robots:" => ESection::Robots,
+                "program:" => ESection::Program,
+                "expect:" => ESection::Expect,
+                _ => {
+                    match section {
+                        ESection::Board => board_lines.push(line),
+                        _ if line.trim().is_empty() => (),
+                        ESection::Description => return Err(syntax_error(number, "expected a section (board:, robots:, program:, expect:)")),
+                        ESection::Robots => robot_lines.push((number, line)),
+                        ESection::Program => program_lines.push((number, line)),
+                        ESection::Expect => expect_lines.push((number, line)),
+                    }
+                    continue;
+                },
+            };
+        }
+
+        let mut grid = board_grid(&board_lines);
+        let mut robots = take_inline_robots(&mut grid);
+        for (number, line) in robot_lines {
+            parse_robot_line(number, line, &mut robots, &mut grid)?;
+        }
+        let board_content: Vec<String> = grid.into_iter().map(|r| r.into_iter().collect()).collect();
+        let board = parse_board(board_content.join("\n"))?;
+
+        let mut programs = vec![];
+        let program_count = program_lines.len();
+        for (index, (number, line)) in program_lines.into_iter().enumerate() {
+            // Higher priorities move first, so players listed first get the higher ones
+            let default_priority = (program_count - index) as u32;
+            let (player_id, registers) = split_player_line(number, line, &robots)?;
+            let cards = registers.split('|')
+                .enumerate()
+                .map(|(register, moves)| parse_register(number, moves, (index * 100 + register) as u32, default_priority))
+                .collect::<Result<Vec<MoveCard>, Error>>()?;
+            programs.push((player_id, cards));
+        }
+
+        let mut expectations = vec![];
+        for (number, line) in expect_lines {
+            let (player_id, expectation) = split_player_line(number, line, &robots)?;
+            expectations.push((player_id, parse_expectation(number, expectation)?));
+        }
+
+        Ok(Scenario {
+            description: description.join(" "),
+            board,
+            robots,
+            programs,
+            expectations,
+        })
+    }
+
+    /// Runs all registers and returns a description for every expectation that is not met
+    pub fn run(&self) -> Result<Vec<String>, Error> {
+        let register_count = self.programs.iter()
+            .map(|(_, cards)| cards.len())
+            .max()
+            .unwrap_or(1);
+
+        let mut players = vec![];
+        for robot in &self.robots {
+            let built_robot = RobotBuilder::default()
+                .id(robot.player_id)
+                .position(robot.position)
+                .direction(robot.direction)
+                .damage(robot.damage)
+                .is_virtual(robot.is_virtual)
+                .build().unwrap();
+            let mut player = Player::new_with_move(robot.player_id, built_robot, MoveCard::new_from_moves(0, 0, &[]));
+            let cards = self.programs.iter()
+                .find(|(player_id, _)| *player_id == robot.player_id)
+                .map(|(_, cards)| cards.clone())
+                .unwrap_or_default();
+            player.registers = (0..register_count)
+                .map(|i| Register {
+                    move_card: Some(cards.get(i).cloned().unwrap_or_else(|| MoveCard::new_from_moves(1000 + i as u32, 0, &[]))),
+                    locked: false,
+                })
+                .collect();
+            players.push(player);
+        }
+
+        let before = State::new_with_random_deck(self.board.clone(), players);
+        let after = RegisterEngine::new().execute_registers(before.clone())?;
+
+        let mut failures = vec![];
+        for (player_id, expected) in &self.expectations {
+            let robot = after.get_robot_by_player_id_or_fail(*player_id)?;
+            let actual = if robot.is_destroyed() {
+                EExpectation::Destroyed
+            } else {
+                let (damage, is_virtual) = match expected {
+                    EExpectation::At{ damage, is_virtual, .. } => (damage.map(|_| robot.damage), is_virtual.map(|_| robot.is_virtual)),
+                    EExpectation::Destroyed => (None, None),
+                };
+                EExpectation::At{ position: robot.position, direction: robot.direction, damage, is_virtual }
+            };
+            if actual != *expected {
+                failures.push(format!("robot {}: expected {:?}, got {:?}", robot_letter(*player_id), expected, actual));
+            }
+        }
+        if !failures.is_empty() {
+            failures.push(render_side_by_side(&before, &after));
+        }
+        Ok(failures)
+    }
+}
+
+fn syntax_error(line: usize, msg: &str) -> Error {
+    ScenarioError::SyntaxError{ line, msg: String::from(msg) }.into()
+}
+
+fn robot_letter(player_id: PlayerID) -> char {
+    (b'A' + player_id as u8) as char
+}
+
+/// The board lines, padded to the same width (editors like to strip trailing whitespace)
+fn board_grid(lines: &[&str]) -> Vec<Vec<char>> {
+    let mut lines: Vec<&str> = lines.to_vec();
+    while lines.last().map_or(false, |l| l.trim().is_empty()) {
+        lines.pop();
+    }
+    let mut grid: Vec<Vec<char>> = lines.iter()
+        .map(|l| l.trim_end().chars().collect())
+        .collect();
+    // Boards start and end with a wall row
+    if grid.len().is_multiple_of(2) {
+        grid.push(vec![]);
+    }
+    let width = grid.iter().map(|r| r.len()).max().unwrap_or(0);
+    let width = width + (width + 1) % 2;
+    for row in &mut grid {
+        row.resize(width, ' ');
+    }
+    grid
+}
+
+/// Replaces the robot letters by regular tiles
+fn take_inline_robots(grid: &mut [Vec<char>]) -> Vec<ScenarioRobot> {
+    let mut robots = vec![];
+    for (row_index, row) in grid.iter_mut().enumerate().filter(|(i, _)| i % 2 == 1) {
+        for (column_index, glyph) in row.iter_mut().enumerate().filter(|(i, _)| i % 2 == 1) {
+            if glyph.is_ascii_uppercase() {
+                robots.push(ScenarioRobot {
+                    player_id: (*glyph as u8 - b'A') as PlayerID,
+                    position: Position::new((column_index / 2) as i32, (row_index / 2) as i32),
+                    direction: EDirection::NORTH,
+                    damage: 0,
+                    is_virtual: false,
+                });
+                *glyph = 'o';
+            }
+        }
+    }
+    robots.sort_by_key(|r| r.player_id);
+    robots
+}
+
+fn parse_robot_line(number: usize, line: &str, robots: &mut [ScenarioRobot], grid: &mut [Vec<char>]) -> Result<(), Error> {
+    let mut tokens = line.split_whitespace();
+    let player_id = parse_letter(number, tokens.next().unwrap_or(""), robots)?;
+    let robot = robots.iter_mut().find(|r| r.player_id == player_id).unwrap();
+    robot.direction = parse_direction(number, tokens.next().unwrap_or(""))?;
+
+    while let Some(key) = tokens.next() {
+        if key == "virtual" {
+            robot.is_virtual = true;
+            continue;
+        }
+        let value = tokens.next().ok_or_else(|| syntax_error(number, &format!("missing value for '{}'", key)))?;
+        match key {
+            "at" => {
+                let coordinates: Vec<Option<i32>> = value.split(',').map(|c| c.parse().ok()).collect();
+                robot.position = match coordinates.as_slice() {
+                    [Some(x), Some(y)] if *x >= 0 && *y >= 0 => Position::new(*x, *y),
+                    _ => return Err(syntax_error(number, "expected 'at <x>,<y>'")),
+                };
+            },
+            "on" => {
+                let glyph = value.chars().next().unwrap();
+                grid[(robot.position.y * 2 + 1) as usize][(robot.position.x * 2 + 1) as usize] = glyph;
+            },
+            "damage" => robot.damage = value.parse().map_err(|_| syntax_error(number, "invalid damage"))?,
+            _ => return Err(syntax_error(number, &format!("unknown robot attribute '{}'", key))),
+        }
+    }
+    Ok(())
+}
+
+fn split_player_line<'a>(number: usize, line: &'a str, robots: &[ScenarioRobot]) -> Result<(PlayerID, &'a str), Error> {
+    let colon = line.find(':').ok_or_else(|| syntax_error(number, "expected '<letter>:'"))?;
+    let player_id = parse_letter(number, line[..colon].trim(), robots)?;
+    Ok((player_id, &line[colon + 1..]))
+}
+
+fn parse_letter(number: usize, letter: &str, robots: &[ScenarioRobot]) -> Result<PlayerID, Error> {
+    let player_id = match letter.as_bytes() {
+        [c] if c.is_ascii_uppercase() => (c - b'A') as PlayerID,
+        _ => return Err(syntax_error(number, &format!("expected a robot letter, got '{}'", letter))),
+    };
+    if !robots.iter().any(|r| r.player_id == player_id) {
+        return Err(syntax_error(number, &format!("robot {} is not on the board", letter)));
+    }
+    Ok(player_id)
+}
+
+fn parse_direction(number: usize, direction: &str) -> Result<EDirection, Error> {
+    match direction {
+        "NORTH" => Ok(EDirection::NORTH),
+        "EAST" => Ok(EDirection::EAST),
+        "SOUTH" => Ok(EDirection::SOUTH),
+        "WEST" => Ok(EDirection::WEST),
+        _ => Err(syntax_error(number, &format!("expected a direction, got '{}'", direction))),
+    }
+}
+
+fn parse_register(number: usize, register: &str, id: u32, default_priority: u32) -> Result<MoveCard, Error> {
+    let mut moves = vec![];
+    let mut priority = default_priority;
+    for token in register.split_whitespace() {
+        let smove = match token {
+            "-" => continue,
+            "Forward" => ESimpleMove::Forward,
+            "Backward" => ESimpleMove::Backward,
+            "StepLeft" => ESimpleMove::StepLeft,
+            "StepRight" => ESimpleMove::StepRight,
+            "TurnLeft" => ESimpleMove::TurnLeft,
+            "TurnRight" => ESimpleMove::TurnRight,
+            "UTurn" => ESimpleMove::UTurn,
+            _ => match token.strip_prefix('@') {
+                Some(p) => {
+                    priority = p.parse().map_err(|_| syntax_error(number, "invalid priority"))?;
+                    continue;
+                },
+                None => return Err(syntax_error(number, &format!("unknown move '{}'", token))),
+            },
+        };
+        moves.push(smove);
+    }
+    Ok(MoveCard::new_from_moves(id, priority, &moves))
+}
+
+fn parse_expectation(number: usize, expectation: &str) -> Result<EExpectation, Error> {
+    let tokens: Vec<&str> = expectation.split_whitespace().collect();
+    let usage = || syntax_error(number, "expected '<x> <y> <direction> [damage <n>] [virtual|real]' or 'destroyed'");
+    let coordinate = |token: &str| token.parse::<i32>().map_err(|_| syntax_error(number, "invalid coordinate"));
+    match tokens.as_slice() {
+        ["destroyed"] => Ok(EExpectation::Destroyed),
+        [x, y, direction, attributes @ ..] => {
+            let mut damage = None;
+            let mut is_virtual = None;
+            let mut attributes = attributes.iter();
+            while let Some(attribute) = attributes.next() {
+                match *attribute {
+                    "damage" => damage = Some(attributes.next()
+                        .and_then(|d| d.parse().ok())
+                        .ok_or_else(|| syntax_error(number, "invalid damage"))?),
+                    "virtual" => is_virtual = Some(true),
+                    "real" => is_virtual = Some(false),
+                    _ => return Err(usage()),
+                }
+            }
+            Ok(EExpectation::At {
+                position: Position::new(coordinate(x)?, coordinate(y)?),
+                direction: parse_direction(number, direction)?,
+                damage,
+                is_virtual,
+            })
+        },
+        _ => Err(usage()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use failure::Error;
+
+    use std::fs;
+    use std::ffi::OsStr;
+    use std::path::PathBuf;
+
+    use super::Scenario;
+
+    const SCENARIOS_DIRECTORY: &str = "./tests/scenarios";
+
+    #[test]
+    fn test_scenarios() -> Result<(), Error> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(SCENARIOS_DIRECTORY)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<_, _>>()?;
+        paths.retain(|p| p.extension() == Some(OsStr::new("scenario")));
+        paths.sort();
+        assert!(!paths.is_empty(), "no scenarios found in {}", SCENARIOS_DIRECTORY);
+
+        let mut failures = vec![];
+        for path in paths {
+            let result = Scenario::parse(&fs::read_to_string(&path)?)
+                .and_then(|scenario| Ok((scenario.run()?, scenario.description)));
+            match result {
+                Ok((errors, _)) if errors.is_empty() => (),
+                Ok((errors, description)) => failures.push(format!("{} ({}):\n{}", path.display(), description, errors.join("\n"))),
+                Err(err) => failures.push(format!("{}: {}", path.display(), err)),
+            }
+        }
+        assert!(failures.is_empty(), "\n{}", failures.join("\n\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        let cases = [
+            ("board:\n\n A \n\nprogram:\nB: Forward\n", "line 6"),
+            ("board:\n\n A \n\nprogram:\nA: Jump\n", "line 6"),
+            ("board:\n\n A \n\nexpect:\nA: 1 NORTH\n", "line 6"),
+            ("board:\n\n A \n\nrobots:\nA UP\n", "line 6"),
+            ("board:\n\n A \n\nrobots:\nA NORTH at 1\n", "line 6"),
+            ("board:\n\n A \n\nexpect:\nA: 0 0 NORTH ghost\n", "line 6"),
+        ];
+        for (content, expected) in &cases {
+            match Scenario::parse(content) {
+                Err(err) => assert!(format!("{}", err).starts_with(expected), "{:?}: {}", content, err),
+                Ok(_) => panic!("Expected an error for {:?}", content),
+            }
+        }
+    }
+}
\ No newline at end of file
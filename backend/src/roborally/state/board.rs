@@ -2,18 +2,33 @@
 
 use derive_builder::Builder;
 
-use super::{ ParserError, StateError };
+use std::sync::Arc;
 
+use super::StateError;
+#[cfg(test)]
+use super::{ ParserError, ParserErrors, DEFAULT_BOARDS_DIRECTORY, BOARD_FILE_EXTENSION, load_board_by_name_from_file };
+
+/// The board played on when none is chosen
+pub const DEFAULT_BOARD: &str = "test-full-1";
+
+/// The board of a game, usually taken from the BoardCatalogue
 #[derive(Debug)]
 pub struct BoardConfig {
-    factory_floor: String,
+    pub board: Arc<Board>,
+}
+
+impl BoardConfig {
+    pub fn new(board: Arc<Board>) -> BoardConfig {
+        BoardConfig {
+            board,
+        }
+    }
 }
 
+#[cfg(test)]
 impl Default for BoardConfig {
     fn default() -> Self {
-        Self {
-            factory_floor: String::from("test-full-1"),
-        }
+        BoardConfig::new(Arc::new(Board::load_board_by_name(DEFAULT_BOARD).unwrap()))
     }
 }
 
@@ -21,7 +36,7 @@ impl Default for BoardConfig {
  * Spans a rectangular board constisting of tiles.
  * Not every tile is playable, [0, 0] is the North-West/upper-left corner
  */
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct Board {
     pub tiles: Vec<Tile>,
     pub size_x: i32,
@@ -29,13 +44,14 @@ pub struct Board {
 }
 
 impl Board {
-    pub fn create_from(config: &BoardConfig) -> Result<Board, ParserError> {
-        super::load_board_by_name(&config.factory_floor)
-    }
-
+    /// Loads a board from DEFAULT_BOARDS_DIRECTORY. Games get their boards from the BoardCatalogue instead.
     #[cfg(test)]
-    pub fn load_board_by_name(name: &str) -> Result<Board, ParserError> {
-        super::load_board_by_name(name)
+    pub fn load_board_by_name(name: &str) -> Result<Board, ParserErrors> {
+        let path = std::path::PathBuf::from(DEFAULT_BOARDS_DIRECTORY).join(format!("{}.{}", name, BOARD_FILE_EXTENSION));
+        if !path.exists() {
+            return Err(ParserError::FileError{ msg: format!("File not found: {}", path.display()) }.into());
+        }
+        load_board_by_name_from_file(&path)
     }
 
     pub fn get_neighbor_in(&self, pos: &Position, direction: EDirection) -> Result<EConnection, StateError> {
@@ -43,12 +59,7 @@ impl Board {
             return Err(StateError::PositionOffBoard{ position: *pos });
         }
 
-        let new_pos = match direction {
-            EDirection::NORTH => pos.set_y(pos.y - 1),
-            EDirection::SOUTH => pos.set_y(pos.y + 1),
-            EDirection::WEST => pos.set_x(pos.x - 1),
-            EDirection::EAST => pos.set_x(pos.x + 1),
-        };
+        let new_pos = pos.neighbor_in(direction);
 
         let old_tile = &self.tiles[self.tile_index(pos)];
         let new_tile = if self.is_off_board(&new_pos) {
@@ -68,6 +79,35 @@ impl Board {
         Ok(EConnection::Free(new_pos))
     }
 
+    /// Whether there is a wall on the given side of pos, no matter on which of the two tiles it is stored
+    pub fn has_wall(&self, pos: &Position, direction: EDirection) -> bool {
+        self.get_tile(pos).map_or(false, |t| t.walls.contains(&direction))
+            || self.get_tile(&pos.neighbor_in(direction)).map_or(false, |t| t.walls.contains(&direction.opposite()))
+    }
+
+    pub fn get_tile(&self, pos: &Position) -> Option<&Tile> {
+        if self.is_off_board(pos) {
+            return None;
+        }
+        self.tiles.get(self.tile_index(pos))
+    }
+
+    /// The position of the other portal with the same label, if pos is a portal and it has exactly one partner
+    pub fn get_portal_partner(&self, pos: &Position) -> Option<Position> {
+        let label = match self.get_tile(pos)?.ttype {
+            ETileType::Portal{ label } => label,
+            _ => return None,
+        };
+        let partners: Vec<Position> = self.tiles.iter()
+            .filter(|t| t.ttype == ETileType::Portal{ label } && t.position != *pos)
+            .map(|t| t.position)
+            .collect();
+        match partners.as_slice() {
+            [partner] => Some(*partner),
+            _ => None,
+        }
+    }
+
     pub fn get_tile_type_at(&self, pos: &Position) -> Result<ETileType, StateError> {
         let index = self.tile_index(pos);
         let tile = self.tiles.get(index)
@@ -86,6 +126,12 @@ impl Board {
         Err(StateError::StartPositionNotFoundID{ start_position_id })
     }
 
+    pub fn start_position_count(&self) -> usize {
+        self.tiles.iter()
+            .filter(|t| t.start_position_id.is_some())
+            .count()
+    }
+
     fn is_off_board(&self, position: &Position) -> bool {
         position.x < 0 || position.x >= self.size_x
             || position.y < 0 || position.y >= self.size_y
@@ -164,7 +210,7 @@ impl Default for EDirection {
 
 pub type StartPositionID = u32;
 
-#[derive(Debug, Builder)]
+#[derive(Debug, Clone, PartialEq, Builder)]
 pub struct Tile {
     pub position: Position,
     pub ttype: ETileType,
@@ -183,11 +229,61 @@ pub enum ETileType {
     },
     Conveyor3 {
         out: EDirection,
-        inputs: [EDirection; 2],
+        inputs: ConveyorInputs,
         express: bool,
     },
     Rotator {
         dir: ERotationDirection,
+    },
+    /// Destroys robots standing on it at the end of the active registers
+    Crusher {
+        registers: ActiveRegisters,
+    },
+    /// Moves robots ending a move on it to the other portal with the same label
+    Portal {
+        label: char,
+    },
+    /// Oil slick or ice: robots moving onto it keep sliding
+    Slippery,
+}
+
+/// The two inputs of a Conveyor3. Their order carries no meaning: they are kept in the order of EDirection, so equal
+/// inputs compare equal whatever order they were given in.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ConveyorInputs([EDirection; 2]);
+
+impl ConveyorInputs {
+    pub fn new(a: EDirection, b: EDirection) -> ConveyorInputs {
+        if (b as u8) < (a as u8) {
+            ConveyorInputs([b, a])
+        } else {
+            ConveyorInputs([a, b])
+        }
+    }
+
+    pub fn as_slice(&self) -> &[EDirection] {
+        &self.0
+    }
+}
+
+/// The registers (by 0-based index) a board element is active in. Only the sets printed on boards exist, each has a
+/// glyph in the .brd format.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ActiveRegisters(u32);
+
+impl ActiveRegisters {
+    pub const ALL: ActiveRegisters = ActiveRegisters(0b11111);
+    /// Registers 1, 3 and 5, as printed on the board
+    pub const ODD: ActiveRegisters = ActiveRegisters(0b10101);
+    /// Registers 2 and 4, as printed on the board
+    pub const EVEN: ActiveRegisters = ActiveRegisters(0b01010);
+
+    pub fn contains(self, register_index: usize) -> bool {
+        register_index < 32 && self.0 & (1 << register_index) != 0
+    }
+
+    pub fn indices(self) -> Vec<usize> {
+        (0..32).filter(|i| self.contains(*i)).collect()
     }
 }
 
@@ -224,4 +320,13 @@ impl Position {
             y,
         }
     }
+
+    pub fn neighbor_in(&self, direction: EDirection) -> Position {
+        match direction {
+            EDirection::NORTH => self.set_y(self.y - 1),
+            EDirection::SOUTH => self.set_y(self.y + 1),
+            EDirection::WEST => self.set_x(self.x - 1),
+            EDirection::EAST => self.set_x(self.x + 1),
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,221 @@
+use failure::Fail;
+
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use super::{ Board, BoardIssue, ParserError, ParserErrors, load_board_by_name_from_file, parse_board };
+
+pub const DEFAULT_BOARDS_DIRECTORY: &str = "./data/boards";
+pub const BOARD_FILE_EXTENSION: &str = "brd";
+
+#[derive(Debug, Fail)]
+pub enum BoardCatalogueError {
+    #[fail(display = "Error while reading boards directory {}: {}", directory, msg)]
+    DirectoryError {
+        directory: String,
+        msg: String,
+    },
+    #[fail(display = "Board not found: {}", name)]
+    BoardNotFound {
+        name: String,
+    },
+    #[fail(display = "Board {} cannot be played, it could not be parsed", name)]
+    BoardNotParsed {
+        name: String,
+    },
+    #[fail(display = "Invalid board name: '{}'", name)]
+    InvalidBoardName {
+        name: String,
+    },
+    #[fail(display = "Board {} rejected:\n{}", name, summary)]
+    BoardRejected {
+        name: String,
+        errors: Vec<ParserError>,
+        issues: Vec<BoardIssue>,
+        /// errors and issues, one per line, as shown to clients
+        summary: String,
+    },
+}
+
+/// A single board known to the catalogue. If the board could not be parsed, `board` is None and `errors` tells why.
+/// Parsed boards are validated, `issues` holds the result.
+#[derive(Debug)]
+pub struct BoardCatalogueEntry {
+    pub name: String,
+    pub board: Option<Arc<Board>>,
+    pub errors: Vec<ParserError>,
+    pub issues: Vec<BoardIssue>,
+}
+
+impl BoardCatalogueEntry {
+    pub fn from_parse_result(name: &str, result: Result<Board, ParserErrors>) -> BoardCatalogueEntry {
+        match result {
+            Ok(board) => BoardCatalogueEntry {
+                name: String::from(name),
+                issues: board.validate(),
+                board: Some(Arc::new(board)),
+                errors: vec![],
+            },
+            Err(err) => BoardCatalogueEntry {
+                name: String::from(name),
+                board: None,
+                errors: err.errors,
+                issues: vec![],
+            },
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.board.is_some() && self.errors.is_empty() && !self.issues.iter().any(|i| i.is_error())
+    }
+}
+
+/// All boards found in the boards directory, parsed once at startup and cached, ordered by name
+#[derive(Debug, Default)]
+pub struct BoardCatalogue {
+    entries: BTreeMap<String, BoardCatalogueEntry>,
+}
+
+impl BoardCatalogue {
+    pub fn load_from_directory(directory: &Path) -> Result<BoardCatalogue, BoardCatalogueError> {
+        let to_catalogue_error = |e: std::io::Error| BoardCatalogueError::DirectoryError {
+            directory: directory.display().to_string(),
+            msg: format!("{}", e),
+        };
+
+        let mut entries = BTreeMap::new();
+        for dir_entry in fs::read_dir(directory).map_err(to_catalogue_error)? {
+            let path = dir_entry.map_err(to_catalogue_error)?.path();
+            if path.extension() != Some(OsStr::new(BOARD_FILE_EXTENSION)) {
+                continue;
+            }
+            let name = match path.file_stem().and_then(OsStr::to_str) {
+                Some(name) => String::from(name),
+                None => continue,
+            };
+
+            let entry = BoardCatalogueEntry::from_parse_result(&name, load_board_by_name_from_file(&path));
+            entries.insert(name, entry);
+        }
+
+        Ok(BoardCatalogue {
+            entries,
+        })
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item=&BoardCatalogueEntry> {
+        self.entries.values()
+    }
+
+    pub fn get_entry_or_fail(&self, name: &str) -> Result<&BoardCatalogueEntry, BoardCatalogueError> {
+        self.entries.get(name)
+            .ok_or_else(|| BoardCatalogueError::BoardNotFound{ name: String::from(name) })
+    }
+
+    /// The board to play on, if it could be parsed
+    pub fn get_board_or_fail(&self, name: &str) -> Result<Arc<Board>, BoardCatalogueError> {
+        self.get_entry_or_fail(name)?.board.clone()
+            .ok_or_else(|| BoardCatalogueError::BoardNotParsed{ name: String::from(name) })
+    }
+
+    /// Parses the given .brd content and, if valid, stores it under the given name (replacing any previous board of
+    /// that name). Invalid boards are not stored, their errors are returned as BoardRejected.
+    pub fn upload_board(&mut self, name: &str, content: String) -> Result<&BoardCatalogueEntry, BoardCatalogueError> {
+        if name.trim().is_empty() {
+            return Err(BoardCatalogueError::InvalidBoardName{ name: String::from(name) });
+        }
+
+        let entry = BoardCatalogueEntry::from_parse_result(name, parse_board(content));
+        if !entry.is_valid() {
+            let summary = entry.errors.iter().map(|e| format!("error: {}", e))
+                .chain(entry.issues.iter().filter(|i| i.is_error()).map(|i| format!("error: {}", i)))
+                .collect::<Vec<String>>()
+                .join("\n");
+            return Err(BoardCatalogueError::BoardRejected{ name: entry.name, errors: entry.errors, issues: entry.issues, summary });
+        }
+        self.entries.insert(String::from(name), entry);
+        self.get_entry_or_fail(name)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use failure::Error;
+
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::super::EBoardIssue;
+    use super::{ BoardCatalogue, BoardCatalogueError, DEFAULT_BOARDS_DIRECTORY };
+
+    #[test]
+    fn test_load_default_directory() -> Result<(), Error> {
+        let catalogue = BoardCatalogue::load_from_directory(&PathBuf::from(DEFAULT_BOARDS_DIRECTORY))?;
+
+        let names: Vec<&str> = catalogue.entries().map(|e| e.name.as_str()).collect();
+        assert!(names.contains(&"empty-5x5"), "empty-5x5 listed");
+        assert!(names.contains(&"test-full-1"), "test-full-1 listed");
+        assert!(catalogue.entries().all(|e| e.is_valid()), "all boards valid");
+
+        let board = catalogue.get_entry_or_fail("test1")?.board.clone().unwrap();
+        assert_eq!(board.size_x, 4, "size_x");
+        assert_eq!(board.size_y, 4, "size_y");
+
+        match catalogue.get_entry_or_fail("does-not-exist") {
+            Err(BoardCatalogueError::BoardNotFound{ .. }) => (),
+            other => panic!("Expected BoardNotFound, got: {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_invalid_board() -> Result<(), Error> {
+        let directory = std::env::temp_dir().join(format!("roborally-catalogue-test-{}", std::process::id()));
+        fs::create_dir_all(&directory)?;
+        fs::write(directory.join("broken.brd"), "     \n o # \n     \n")?;
+        fs::write(directory.join("ignored.txt"), "not a board")?;
+
+        let catalogue = BoardCatalogue::load_from_directory(&directory)?;
+        fs::remove_dir_all(&directory)?;
+
+        assert_eq!(catalogue.entries().count(), 1, "only .brd files listed");
+        let entry = catalogue.get_entry_or_fail("broken")?;
+        assert!(!entry.is_valid(), "broken is invalid");
+        assert!(entry.board.is_none(), "broken has no board");
+        assert_eq!(entry.errors.len(), 1, "broken has an error");
+        Ok(())
+    }
+
+    #[test]
+    fn test_upload_board() -> Result<(), Error> {
+        let mut catalogue = BoardCatalogue::default();
+
+        let entry = catalogue.upload_board("custom", String::from("     \n o 1 \n     \n"))?;
+        assert!(entry.is_valid(), "custom is valid");
+        assert_eq!(entry.issues.iter().map(|i| i.issue.clone()).collect::<Vec<EBoardIssue>>(), vec![EBoardIssue::FewStartPositions{ start_position_count: 1 }], "warned about start positions");
+        assert_eq!(catalogue.entries().count(), 1, "custom stored");
+
+        match catalogue.upload_board("custom", String::from("     \n o # \n     \n")) {
+            Err(BoardCatalogueError::BoardRejected{ errors, summary, .. }) => {
+                assert_eq!(errors.len(), 1, "one error");
+                let location = errors[0].location().unwrap();
+                assert_eq!((location.line, location.column), (2, 4), "error location");
+                assert_eq!(errors[0].glyph(), Some('#'), "error glyph");
+                assert!(!summary.contains("ParserError"), "summary without debug output: {}", summary);
+                assert!(summary.starts_with("error: "), "summary lists the error: {}", summary);
+            },
+            other => panic!("Expected BoardRejected, got: {:?}", other),
+        }
+        let entry = catalogue.get_entry_or_fail("custom")?;
+        assert_eq!(entry.board.as_ref().unwrap().start_position_count(), 1, "previous board kept");
+
+        match catalogue.upload_board(" ", String::from("   \n o \n   \n")) {
+            Err(BoardCatalogueError::InvalidBoardName{ .. }) => (),
+            other => panic!("Expected InvalidBoardName, got: {:?}", other),
+        }
+        Ok(())
+    }
+}
\ No newline at end of file
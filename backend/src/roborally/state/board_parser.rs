@@ -1,12 +1,12 @@
 #![allow(clippy::trivially_copy_pass_by_ref)]
 use failure::Fail;
 
-use std::path::{ Path, PathBuf };
+use std::path::Path;
+use std::fmt;
 use std::fs;
-use std::str::Chars;
 use std::collections::HashSet;
 
-use super::{ Board, ETileType, ERotationDirection, Tile, Position, EDirection, StartPositionID };
+use super::{ ActiveRegisters, Board, ConveyorInputs, ETileType, ERotationDirection, Tile, Position, EDirection, StartPositionID };
 
 #[derive(Debug, Fail)]
 pub enum ParserError {
@@ -14,47 +14,150 @@ pub enum ParserError {
     FileError {
         msg: String,
     },
-    #[fail(display = "Expected wall, found: '{}'", msg)]
+    #[fail(display = "Expected wall ('|' or ' '), found: '{}'{}", glyph, location)]
     WallNotFound {
-        msg: String,
+        glyph: char,
+        location: SourceLocation,
+    },
+    #[fail(display = "Expected horizontal wall ('-' or ' '), found: '{}'{}", glyph, location)]
+    HorizontalWallNotFound {
+        glyph: char,
+        location: SourceLocation,
     },
-    #[fail(display = "Expected ETileType, found: '{}'", msg)]
+    #[fail(display = "Expected ETileType, found: '{}'{}", glyph, location)]
     UnknownTileType {
-        msg: String,
+        glyph: char,
+        location: SourceLocation,
     },
-    #[fail(display = "Unexpected end of row: {:?}", position)]
+    #[fail(display = "Unexpected end of row{}", location)]
     EndOfRow {
-        position: Position,
-    },
-    #[fail(display = "Unexpected end of row")]
-    TileEndOfRow {
+        location: SourceLocation,
     },
-    #[fail(display = "Unexpected row length {}: before ({}), after ({})", index, before, after)]
+    #[fail(display = "Unexpected row length: expected {} tiles, found {}{}", expected, actual, location)]
     UnexpectedRowLength {
-        index: i32,
-        before: usize,
-        after: usize,
+        expected: usize,
+        actual: usize,
+        location: SourceLocation,
     },
-    #[fail(display = "Missing tile for hwall at: {:?}", position)]
+    #[fail(display = "Missing tile for hwall at: {:?}{}", position, location)]
     MissingTileForHWall {
         position: Position,
+        location: SourceLocation,
     },
-    #[fail(display = "Board contains the same start position id multiple times: {} {:?}", id, position)]
+    #[fail(display = "Board contains the same start position id multiple times: {} {:?}{}", id, position, location)]
     DuplicateStartPositionId {
         id: u32,
         position: Position,
+        location: SourceLocation,
     },
 }
 
-pub fn load_board_by_name(name: &str) -> Result<Board, ParserError> {
-    let base_path = PathBuf::from(format!("./data/boards/{}.brd", name));
-    if !base_path.exists() {
-        return Err(ParserError::FileError{ msg: format!("File not found: {}", base_path.display()) });
+impl ParserError {
+    /// Where in the board file this error has been found, if it's about the content
+    pub fn location(&self) -> Option<&SourceLocation> {
+        match self {
+            ParserError::FileError{ .. } => None,
+            ParserError::WallNotFound{ location, .. } => Some(location),
+            ParserError::HorizontalWallNotFound{ location, .. } => Some(location),
+            ParserError::UnknownTileType{ location, .. } => Some(location),
+            ParserError::EndOfRow{ location } => Some(location),
+            ParserError::UnexpectedRowLength{ location, .. } => Some(location),
+            ParserError::MissingTileForHWall{ location, .. } => Some(location),
+            ParserError::DuplicateStartPositionId{ location, .. } => Some(location),
+        }
+    }
+
+    /// The glyph that could not be parsed, if any
+    pub fn glyph(&self) -> Option<char> {
+        match self {
+            ParserError::WallNotFound{ glyph, .. } => Some(*glyph),
+            ParserError::HorizontalWallNotFound{ glyph, .. } => Some(*glyph),
+            ParserError::UnknownTileType{ glyph, .. } => Some(*glyph),
+            _ => None,
+        }
+    }
+}
+
+/// All errors found in a board file. The parser does not stop at the first one.
+#[derive(Debug)]
+pub struct ParserErrors {
+    pub errors: Vec<ParserError>,
+}
+
+impl From<ParserError> for ParserErrors {
+    fn from(err: ParserError) -> ParserErrors {
+        ParserErrors {
+            errors: vec![err],
+        }
+    }
+}
+
+impl Fail for ParserErrors {}
+
+impl fmt::Display for ParserErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, err) in self.errors.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            writeln!(f, "error: {}", err)?;
+        }
+        Ok(())
     }
-    load_board_by_name_from_file(&base_path)
 }
 
-pub fn load_board_by_name_from_file(path: &Path) -> Result<Board, ParserError> {
+/// .brd files alternate between rows of horizontal walls and rows of tiles (with the vertical walls in between)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ERowKind {
+    Wall,
+    Tile,
+}
+
+/// Where in a board file something has been found. line and column are 1-based, like in any editor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceLocation {
+    pub line: usize,
+    pub column: usize,
+    pub row_kind: ERowKind,
+    pub line_content: String,
+}
+
+/// Prints the offending line with a caret pointing at the column, like a compiler does
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let row_kind = match self.row_kind {
+            ERowKind::Wall => "wall row",
+            ERowKind::Tile => "tile row",
+        };
+        let line_number = self.line.to_string();
+        let gutter = " ".repeat(line_number.len());
+        writeln!(f)?;
+        writeln!(f, "{}--> line {}, column {} ({})", gutter, self.line, self.column, row_kind)?;
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", line_number, self.line_content)?;
+        write!(f, "{} | {}^", gutter, " ".repeat(self.column.saturating_sub(1)))
+    }
+}
+
+struct SourceLine<'a> {
+    number: usize,
+    content: &'a str,
+    row_kind: ERowKind,
+}
+
+impl SourceLine<'_> {
+    /// column_index is the 0-based index of the char in this line
+    fn location(&self, column_index: usize) -> SourceLocation {
+        SourceLocation {
+            line: self.number,
+            column: column_index + 1,
+            row_kind: self.row_kind,
+            line_content: String::from(self.content),
+        }
+    }
+}
+
+pub fn load_board_by_name_from_file(path: &Path) -> Result<Board, ParserErrors> {
     let content = match fs::read_to_string(path) {
         Ok(c) => Ok(c),
         Err(e) => Err(ParserError::FileError{ msg: format!("{}", e) }),
@@ -63,40 +166,60 @@ pub fn load_board_by_name_from_file(path: &Path) -> Result<Board, ParserError> {
     parse_board(content)
 }
 
-fn parse_board(content: String) -> Result<Board, ParserError> {
+pub fn parse_board(content: String) -> Result<Board, ParserErrors> {
+    let mut errors: Vec<ParserError> = vec![];
+    let mut start_ids_set: HashSet<StartPositionID> = HashSet::new();
     let mut tiles: Vec<Tile> = vec![];
     let mut hwalls: Vec<HWall> = vec![];
     let mut y_raw = 0;
     let mut y = 0;
     let mut x = 0;
-    for row_str in content.split('\n') {
+    for (line_index, row_str) in content.split('\n').enumerate() {
         if row_str.is_empty() {
             continue
         }
 
         if y_raw % 2 == 0 {
-            let row_walls = parse_horizontal_wall_row(row_str, y)?;
+            let line = SourceLine{ number: line_index + 1, content: row_str, row_kind: ERowKind::Wall };
+            let row_walls = parse_horizontal_wall_row(&line, y, &mut errors);
             let row_length = row_walls.len();
             if x == 0 {
                 x = row_length;
             } else if x != row_length {
-                return Err(ParserError::UnexpectedRowLength{ index: y_raw, before: x, after: row_length })
+                let location = line.location(x.min(row_length) * 2 + 1);
+                errors.push(ParserError::UnexpectedRowLength{ expected: x, actual: row_length, location });
             }
             hwalls.extend(row_walls);
         } else {
-            let row_tiles = parse_tile_row(row_str, y)?;
+            let line = SourceLine{ number: line_index + 1, content: row_str, row_kind: ERowKind::Tile };
+            let row_tiles = parse_tile_row(&line, y, &mut errors);
             if x != row_tiles.len() {
-                return Err(ParserError::UnexpectedRowLength{ index: y_raw, before: x, after: row_tiles.len() })
+                let location = line.location(x.min(row_tiles.len()) * 2 + 1);
+                errors.push(ParserError::UnexpectedRowLength{ expected: x, actual: row_tiles.len(), location });
+            }
+
+            for (tile, location) in row_tiles {
+                // Validate that each start position is unique
+                if let Some(id) = tile.start_position_id {
+                    if !start_ids_set.insert(id) {
+                        errors.push(ParserError::DuplicateStartPositionId{ id, position: tile.position, location });
+                    }
+                }
+                tiles.push(tile);
             }
-            tiles.extend(row_tiles);
             y += 1;
         }
         y_raw += 1;
     }
 
+    // Tiles are misaligned if any row is broken: wall positions would only produce follow-up errors
+    if !errors.is_empty() {
+        return Err(ParserErrors{ errors });
+    }
+
     let x = x as i32;
     for hwall in hwalls {
-        if hwall.wall {
+        if let Some(location) = hwall.wall {
             let mut position = hwall.position;
             let mut direction = EDirection::NORTH;
             if hwall.position.y == y {
@@ -109,21 +232,18 @@ fn parse_board(content: String) -> Result<Board, ParserError> {
             };
             let index = index(&position, x);
             let tile = match tiles.get_mut(index) {
-                Some(t) => t,
-                None => return Err(ParserError::MissingTileForHWall{ position }),
+                Some(t) if position.y >= 0 => t,
+                _ => {
+                    errors.push(ParserError::MissingTileForHWall{ position, location });
+                    continue;
+                },
             };
             tile.walls.push(direction);
         }
     }
 
-    // Validate that each start position is unique
-    let mut start_ids_set: HashSet<StartPositionID> = HashSet::new();
-    for tile in &tiles {
-        if let Some(start_pos_id) = tile.start_position_id {
-            if !start_ids_set.insert(start_pos_id) {
-                return Err(ParserError::DuplicateStartPositionId{ id: start_pos_id, position: tile.position })
-            }
-        }
+    if !errors.is_empty() {
+        return Err(ParserErrors{ errors });
     }
 
     Ok(Board {
@@ -139,19 +259,24 @@ fn index(pos: &Position, x_size: i32) -> usize {
 
 struct HWall {
     position: Position,
-    wall: bool,
+    /// If there is a wall: where it is in the source
+    wall: Option<SourceLocation>,
 }
-fn parse_horizontal_wall_row(row_str: &str, y: i32) -> Result<Vec<HWall>, ParserError> {
+fn parse_horizontal_wall_row(line: &SourceLine, y: i32, errors: &mut Vec<ParserError>) -> Vec<HWall> {
     let mut walls: Vec<HWall> = vec![];
     let mut x = 0;
-    let mut chars = row_str.chars();
+    let mut chars = line.content.chars();
     loop {
-        let wall = match &[chars.next(), chars.next()] {
-            [_, Some('-')] => Ok(true),
-            [_, Some(' ')] => Ok(false),
-            [_, None] => return Ok(walls),
-            _ => Err(ParserError::EndOfRow{ position: Position{ x, y }}),
-        }?;
+        let column_index = x as usize * 2 + 1;
+        let wall = match [chars.next(), chars.next()] {
+            [_, Some('-')] => Some(line.location(column_index)),
+            [_, Some(' ')] => None,
+            [_, None] => return walls,
+            [_, Some(c)] => {
+                errors.push(ParserError::HorizontalWallNotFound{ glyph: c, location: line.location(column_index) });
+                None
+            },
+        };
         walls.push(HWall{
             position: Position{ x, y },
             wall,
@@ -160,121 +285,151 @@ fn parse_horizontal_wall_row(row_str: &str, y: i32) -> Result<Vec<HWall>, Parser
     }
 }
 
-fn parse_tile_row(row_str: &str, y: i32) -> Result<Vec<Tile>, ParserError> {
-    let mut tiles: Vec<Tile> = vec![];
+/// Returns the tiles of this row along with their location in the source
+fn parse_tile_row(line: &SourceLine, y: i32, errors: &mut Vec<ParserError>) -> Vec<(Tile, SourceLocation)> {
+    let mut tiles: Vec<(Tile, SourceLocation)> = vec![];
     let mut x = 0;
-    let mut chars = row_str.chars();
+    let mut chars = line.content.chars();
     loop {
-        let wall = match_vertical_wall(&mut chars)?;
-        let (tile_type, start_position_id) = match match_tile_type(&mut chars) {
-            Ok(t) => t,
-            Err(ParserError::TileEndOfRow{}) => {
-                if tiles.is_empty() {
-                    return Err(ParserError::EndOfRow{ position: Position{ x, y }});
-                }
+        let column_index = x as usize * 2;
+        let wall = match chars.next() {
+            Some('|') => true,
+            Some(' ') => false,
+            Some(c) => {
+                errors.push(ParserError::WallNotFound{ glyph: c, location: line.location(column_index) });
+                false
+            },
+            None => {
+                errors.push(ParserError::EndOfRow{ location: line.location(column_index) });
+                break;
+            },
+        };
 
-                if wall {
+        let location = line.location(column_index + 1);
+        let (tile_type, start_position_id) = match chars.next() {
+            None => {
+                if tiles.is_empty() {
+                    errors.push(ParserError::EndOfRow{ location });
+                } else if wall {
                     let last_index = tiles.len() - 1;
-                    tiles[last_index].walls.push(EDirection::EAST);
+                    tiles[last_index].0.walls.push(EDirection::EAST);
                 }
                 break;
             },
-            Err(err) => return Err(err),
+            Some(c) => match match_tile_type(c) {
+                Some(t) => t,
+                None => {
+                    // Keep going to find further errors in this row
+                    errors.push(ParserError::UnknownTileType{ glyph: c, location: location.clone() });
+                    (ETileType::NoTile, None)
+                },
+            },
         };
-        
-        tiles.push(Tile {
+
+        tiles.push((Tile {
             position: Position{ x, y },
             ttype: tile_type,
             walls: if wall { vec![EDirection::WEST] } else { vec![] },
             start_position_id,
-        });
-        
+        }, location));
+
         x += 1;
     }
-    Ok(tiles)
+    tiles
 }
 
-fn match_vertical_wall(chars: &mut Chars) -> Result<bool, ParserError> {
-    match chars.next() {
-        Some('|') => Ok(true),
-        Some(' ') => Ok(false),
-        Some(c) => Err(ParserError::WallNotFound{ msg: c.to_string() }),
-        None => Err(ParserError::TileEndOfRow{}),
+fn match_tile_type(glyph: char) -> Option<(ETileType, Option<u32>)> {
+    match tile_type_from_glyph(glyph) {
+        Some(tile_type) => Some((tile_type, None)),
+        None => glyph.to_digit(10).map(|start_position_id| (ETileType::Regular, Some(start_position_id))),
     }
 }
 
-fn match_tile_type(chars: &mut Chars) -> Result<(ETileType, Option<u32>), ParserError> {
+/// All glyphs which denote a tile type (see tile_type_from_glyph). Start positions are denoted by their (single digit)
+/// id on a Regular tile, portals by a lowercase label shared with their partner.
+pub const TILE_TYPE_GLYPHS: &str = "o ↻↺↓←→↑↡↞↠↟┑┎┕┚┍┒┙┖╕╓╘╜╒╖╛╙┝┞┟┥┦┧┭┮┰┵┶┸▣◧◨≈abcdefghijklmnpqrstuvwxyz";
+
+pub fn tile_type_from_glyph(glyph: char) -> Option<ETileType> {
     use ETileType::*;
     use EDirection::*;
     use ERotationDirection::*;
 
-    match chars.next() {
-        None => Err(ParserError::TileEndOfRow{}),
-        Some(c) => match c {
-            '\n' => Err(ParserError::TileEndOfRow{}),
-
-            'o' => Ok((Regular, None)),
-            ' ' => Ok((NoTile, None)),
-            '↻' => Ok((Rotator { dir: Right }, None)),
-            '↺' => Ok((Rotator { dir: Left }, None)),
-
-            '↓' => Ok((Conveyor2 { input: NORTH, out: SOUTH, express: false }, None)),
-            '←' => Ok((Conveyor2 { input: EAST, out: WEST, express: false }, None)),
-            '→' => Ok((Conveyor2 { input: WEST, out: EAST, express: false }, None)),
-            '↑' => Ok((Conveyor2 { input: SOUTH, out: NORTH, express: false }, None)),
-            '↡' => Ok((Conveyor2 { input: NORTH, out: SOUTH, express: true }, None)),
-            '↞' => Ok((Conveyor2 { input: EAST, out: WEST, express: true }, None)),
-            '↠' => Ok((Conveyor2 { input: WEST, out: EAST, express: true }, None)),
-            '↟' => Ok((Conveyor2 { input: SOUTH, out: NORTH, express: true }, None)),
-
-            '┑' => Ok((Conveyor2 { input: SOUTH, out: WEST, express: false }, None)),
-            '┎' => Ok((Conveyor2 { input: EAST, out: SOUTH, express: false }, None)),
-            '┕' => Ok((Conveyor2 { input: NORTH, out: EAST, express: false }, None)),
-            '┚' => Ok((Conveyor2 { input: WEST, out: NORTH, express: false }, None)),
-            '┍' => Ok((Conveyor2 { input: SOUTH, out: EAST, express: false }, None)),
-            '┒' => Ok((Conveyor2 { input: WEST, out: SOUTH, express: false }, None)),
-            '┙' => Ok((Conveyor2 { input: NORTH, out: WEST, express: false }, None)),
-            '┖' => Ok((Conveyor2 { input: EAST, out: NORTH, express: false }, None)),
-
-            '╕' => Ok((Conveyor2 { input: SOUTH, out: WEST, express: true }, None)),
-            '╓' => Ok((Conveyor2 { input: EAST, out: SOUTH, express: true }, None)),
-            '╘' => Ok((Conveyor2 { input: NORTH, out: EAST, express: true }, None)),
-            '╜' => Ok((Conveyor2 { input: WEST, out: NORTH, express: true }, None)),
-            '╒' => Ok((Conveyor2 { input: SOUTH, out: EAST, express: true }, None)),
-            '╖' => Ok((Conveyor2 { input: WEST, out: SOUTH, express: true }, None)),
-            '╛' => Ok((Conveyor2 { input: NORTH, out: WEST, express: true }, None)),
-            '╙' => Ok((Conveyor2 { input: EAST, out: NORTH, express: true }, None)),
-
-            '┝' => Ok((Conveyor3 { inputs: [NORTH, SOUTH], out: EAST, express: false }, None)),
-            '┞' => Ok((Conveyor3 { inputs: [EAST, SOUTH], out: NORTH, express: false }, None)),
-            '┟' => Ok((Conveyor3 { inputs: [NORTH, EAST], out: SOUTH, express: false }, None)),
-
-            '┥' => Ok((Conveyor3 { inputs: [NORTH, SOUTH], out: WEST, express: false }, None)),
-            '┦' => Ok((Conveyor3 { inputs: [WEST, SOUTH], out: NORTH, express: false }, None)),
-            '┧' => Ok((Conveyor3 { inputs: [NORTH, WEST], out: SOUTH, express: false }, None)),
-
-            '┭' => Ok((Conveyor3 { inputs: [EAST, SOUTH], out: WEST, express: false }, None)),
-            '┮' => Ok((Conveyor3 { inputs: [WEST, SOUTH], out: EAST, express: false }, None)),
-            '┰' => Ok((Conveyor3 { inputs: [WEST, EAST], out: SOUTH, express: false }, None)),
-
-            '┵' => Ok((Conveyor3 { inputs: [NORTH, EAST], out: WEST, express: false }, None)),
-            '┶' => Ok((Conveyor3 { inputs: [NORTH, WEST], out: EAST, express: false }, None)),
-            '┸' => Ok((Conveyor3 { inputs: [WEST, EAST], out: NORTH, express: false }, None)),
-            
-            c => match c.to_digit(10) {
-                Some(start_position_id) => Ok((ETileType::Regular, Some(start_position_id))),
-                None => Err(ParserError::UnknownTileType{ msg: c.to_string() }),
-            }
-        }
+    match glyph {
+        'o' => Some(Regular),
+        ' ' => Some(NoTile),
+        '↻' => Some(Rotator { dir: Right }),
+        '↺' => Some(Rotator { dir: Left }),
+
+        '↓' => Some(Conveyor2 { input: NORTH, out: SOUTH, express: false }),
+        '←' => Some(Conveyor2 { input: EAST, out: WEST, express: false }),
+        '→' => Some(Conveyor2 { input: WEST, out: EAST, express: false }),
+        '↑' => Some(Conveyor2 { input: SOUTH, out: NORTH, express: false }),
+        '↡' => Some(Conveyor2 { input: NORTH, out: SOUTH, express: true }),
+        '↞' => Some(Conveyor2 { input: EAST, out: WEST, express: true }),
+        '↠' => Some(Conveyor2 { input: WEST, out: EAST, express: true }),
+        '↟' => Some(Conveyor2 { input: SOUTH, out: NORTH, express: true }),
+
+        '┑' => Some(Conveyor2 { input: SOUTH, out: WEST, express: false }),
+        '┎' => Some(Conveyor2 { input: EAST, out: SOUTH, express: false }),
+        '┕' => Some(Conveyor2 { input: NORTH, out: EAST, express: false }),
+        '┚' => Some(Conveyor2 { input: WEST, out: NORTH, express: false }),
+        '┍' => Some(Conveyor2 { input: SOUTH, out: EAST, express: false }),
+        '┒' => Some(Conveyor2 { input: WEST, out: SOUTH, express: false }),
+        '┙' => Some(Conveyor2 { input: NORTH, out: WEST, express: false }),
+        '┖' => Some(Conveyor2 { input: EAST, out: NORTH, express: false }),
+
+        '╕' => Some(Conveyor2 { input: SOUTH, out: WEST, express: true }),
+        '╓' => Some(Conveyor2 { input: EAST, out: SOUTH, express: true }),
+        '╘' => Some(Conveyor2 { input: NORTH, out: EAST, express: true }),
+        '╜' => Some(Conveyor2 { input: WEST, out: NORTH, express: true }),
+        '╒' => Some(Conveyor2 { input: SOUTH, out: EAST, express: true }),
+        '╖' => Some(Conveyor2 { input: WEST, out: SOUTH, express: true }),
+        '╛' => Some(Conveyor2 { input: NORTH, out: WEST, express: true }),
+        '╙' => Some(Conveyor2 { input: EAST, out: NORTH, express: true }),
+
+        '┝' => Some(Conveyor3 { inputs: ConveyorInputs::new(NORTH, SOUTH), out: EAST, express: false }),
+        '┞' => Some(Conveyor3 { inputs: ConveyorInputs::new(EAST, SOUTH), out: NORTH, express: false }),
+        '┟' => Some(Conveyor3 { inputs: ConveyorInputs::new(NORTH, EAST), out: SOUTH, express: false }),
+
+        '┥' => Some(Conveyor3 { inputs: ConveyorInputs::new(NORTH, SOUTH), out: WEST, express: false }),
+        '┦' => Some(Conveyor3 { inputs: ConveyorInputs::new(WEST, SOUTH), out: NORTH, express: false }),
+        '┧' => Some(Conveyor3 { inputs: ConveyorInputs::new(NORTH, WEST), out: SOUTH, express: false }),
+
+        '┭' => Some(Conveyor3 { inputs: ConveyorInputs::new(EAST, SOUTH), out: WEST, express: false }),
+        '┮' => Some(Conveyor3 { inputs: ConveyorInputs::new(WEST, SOUTH), out: EAST, express: false }),
+        '┰' => Some(Conveyor3 { inputs: ConveyorInputs::new(WEST, EAST), out: SOUTH, express: false }),
+
+        '┵' => Some(Conveyor3 { inputs: ConveyorInputs::new(NORTH, EAST), out: WEST, express: false }),
+        '┶' => Some(Conveyor3 { inputs: ConveyorInputs::new(NORTH, WEST), out: EAST, express: false }),
+        '┸' => Some(Conveyor3 { inputs: ConveyorInputs::new(WEST, EAST), out: NORTH, express: false }),
+
+        '▣' => Some(Crusher { registers: ActiveRegisters::ALL }),
+        '◧' => Some(Crusher { registers: ActiveRegisters::ODD }),
+        '◨' => Some(Crusher { registers: ActiveRegisters::EVEN }),
+
+        '≈' => Some(Slippery),
+
+        'a'..='z' if glyph != 'o' => Some(Portal { label: glyph }),
+
+        _ => None,
     }
 }
 
+/// The inverse of tile_type_from_glyph
+pub fn glyph_from_tile_type(ttype: ETileType) -> Option<char> {
+    TILE_TYPE_GLYPHS.chars()
+        .find(|c| tile_type_from_glyph(*c) == Some(ttype))
+}
+
 #[cfg(test)]
 mod test {
     use failure::Error;
+    use proptest::prelude::*;
+    use proptest::collection::vec;
+    use proptest::sample::select;
 
-    use super::super::{ Board, ETileType, Tile, Position, EDirection };
-    use super::{ parse_board, index };
+    use super::super::{ Board, ConveyorInputs, ETileType, Tile, Position, EDirection };
+    use super::{ parse_board, index, tile_type_from_glyph, ParserError, ERowKind, TILE_TYPE_GLYPHS };
 
 
     #[test]
@@ -353,6 +508,114 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_conveyor3_inputs_in_any_order() {
+        use EDirection::*;
+        assert_eq!(ConveyorInputs::new(SOUTH, NORTH), ConveyorInputs::new(NORTH, SOUTH), "inputs");
+        assert_eq!(ConveyorInputs::new(WEST, EAST).as_slice(), &[EAST, WEST], "sorted");
+        let swapped = ETileType::Conveyor3{ inputs: ConveyorInputs::new(SOUTH, NORTH), out: EAST, express: false };
+        assert_eq!(tile_type_from_glyph('┝'), Some(swapped), "tile type");
+    }
+
+    #[test]
+    fn test_parse_board_error_location() {
+        let content = "
+     
+ o o 
+     
+ o|# 
+     ";
+        let errors = parse_board(String::from(content)).unwrap_err().errors;
+        match errors.as_slice() {
+            [err @ ParserError::UnknownTileType{ .. }] => {
+                let location = err.location().unwrap();
+                assert_eq!((location.line, location.column), (5, 4), "location");
+                assert_eq!(location.row_kind, ERowKind::Tile, "row kind");
+                assert_eq!(err.glyph(), Some('#'), "glyph");
+            },
+            other => panic!("Expected UnknownTileType, got: {:?}", other),
+        }
+
+        let content = "
+     
+ o*o 
+     ";
+        let errors = parse_board(String::from(content)).unwrap_err().errors;
+        match errors.as_slice() {
+            [err @ ParserError::WallNotFound{ .. }] => {
+                let location = err.location().unwrap();
+                assert_eq!((location.line, location.column), (3, 3), "location");
+                assert_eq!(err.glyph(), Some('*'), "glyph");
+            },
+            other => panic!("Expected WallNotFound, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_board_collects_all_errors() {
+        let content = [
+            "       ",
+            " o # o ",
+            "   +   ",
+            " 1 o 1 ",
+            "       ",
+            " o o ",
+            "       ",
+        ].join("\n");
+        let errors = parse_board(content).unwrap_err().errors;
+        let found: Vec<(usize, usize, ERowKind)> = errors.iter()
+            .map(|e| e.location().unwrap())
+            .map(|l| (l.line, l.column, l.row_kind))
+            .collect();
+        assert_eq!(found, vec![
+            (2, 4, ERowKind::Tile),     // unknown tile '#'
+            (3, 4, ERowKind::Wall),     // unknown hwall '+'
+            (4, 6, ERowKind::Tile),     // duplicate start position 1
+            (6, 6, ERowKind::Tile),     // row too short
+        ], "{:?}", errors);
+        match &errors[3] {
+            ParserError::UnexpectedRowLength{ expected: 3, actual: 2, .. } => (),
+            other => panic!("Expected UnexpectedRowLength, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parser_error_display() {
+        let content = [
+            "     ",
+            " o o ",
+            "     ",
+            " o|# ",
+            "     ",
+        ].join("\n");
+        let errors = parse_board(content).unwrap_err();
+        let expected = [
+            "error: Expected ETileType, found: '#'",
+            " --> line 4, column 4 (tile row)",
+            "  |",
+            "4 |  o|# ",
+            "  |    ^",
+            "",
+        ].join("\n");
+        assert_eq!(format!("{}", errors), expected);
+    }
+
+    /// Mostly characters that occur in .brd files, so the parser gets past the first row now and then
+    fn arb_board_content() -> impl Strategy<Value = String> {
+        let alphabet: Vec<char> = format!("{}|-\n\n\n0123456789#", TILE_TYPE_GLYPHS).chars().collect();
+        prop_oneof![
+            vec(select(alphabet), 0..300).prop_map(|chars| chars.into_iter().collect()),
+            any::<String>(),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn test_parse_board_never_panics(content in arb_board_content()) {
+            let _ = parse_board(content);
+        }
+    }
+
     fn compare_boards(exp_board: &Board, act_board: &Board) -> Result<(), Error> {
         assert_eq!(exp_board.size_x, act_board.size_x, "size_x");
         assert_eq!(exp_board.size_y, act_board.size_y, "size_y");
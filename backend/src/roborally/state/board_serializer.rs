@@ -0,0 +1,230 @@
+#![allow(clippy::trivially_copy_pass_by_ref)]
+use failure::Fail;
+
+use super::{ Board, ETileType, Tile, Position, EDirection, glyph_from_tile_type };
+
+#[derive(Debug, Fail)]
+pub enum SerializerError {
+    #[fail(display = "No glyph for tile type {:?} at: {:?}", ttype, position)]
+    UnsupportedTileType {
+        ttype: ETileType,
+        position: Position,
+    },
+    #[fail(display = "Start position {} at {:?} can't be written (only 0-9 on regular tiles)", id, position)]
+    UnsupportedStartPosition {
+        id: u32,
+        position: Position,
+    },
+    #[fail(display = "Board size ({}x{}) does not match its tile count {}", size_x, size_y, tile_count)]
+    InconsistentSize {
+        size_x: i32,
+        size_y: i32,
+        tile_count: usize,
+    },
+}
+
+/// Renders a board into the .brd format, the inverse of parse_board.
+/// Walls between two tiles may be stored on either of them, they end up as a single wall glyph.
+pub fn serialize_board(board: &Board) -> Result<String, SerializerError> {
+    if board.tiles.len() != (board.size_x * board.size_y) as usize {
+        return Err(SerializerError::InconsistentSize{ size_x: board.size_x, size_y: board.size_y, tile_count: board.tiles.len() });
+    }
+
+    let mut rows: Vec<String> = Vec::with_capacity((board.size_y * 2 + 1) as usize);
+    for y in 0..board.size_y {
+        rows.push(serialize_horizontal_wall_row(board, y));
+        rows.push(serialize_tile_row(board, y)?);
+    }
+    rows.push(serialize_horizontal_wall_row(board, board.size_y));
+    Ok(rows.join("\n"))
+}
+
+/// Walls between row y - 1 and row y
+fn serialize_horizontal_wall_row(board: &Board, y: i32) -> String {
+    let mut row = String::with_capacity((board.size_x * 2 + 1) as usize);
+    for x in 0..board.size_x {
+        let wall = board.has_wall(&Position{ x, y }, EDirection::NORTH);
+        row.push(' ');
+        row.push(if wall { '-' } else { ' ' });
+    }
+    row.push(' ');
+    row
+}
+
+fn serialize_tile_row(board: &Board, y: i32) -> Result<String, SerializerError> {
+    let mut row = String::with_capacity((board.size_x * 2 + 1) as usize);
+    for x in 0..board.size_x {
+        let position = Position{ x, y };
+        let wall = board.has_wall(&position, EDirection::WEST);
+        row.push(if wall { '|' } else { ' ' });
+        row.push(tile_glyph(&board.tiles[index(&position, board.size_x)])?);
+    }
+    let last_wall = board.has_wall(&Position{ x: board.size_x - 1, y }, EDirection::EAST);
+    row.push(if last_wall { '|' } else { ' ' });
+    Ok(row)
+}
+
+fn tile_glyph(tile: &Tile) -> Result<char, SerializerError> {
+    if let Some(id) = tile.start_position_id {
+        return match std::char::from_digit(id, 10) {
+            Some(c) if tile.ttype == ETileType::Regular => Ok(c),
+            _ => Err(SerializerError::UnsupportedStartPosition{ id, position: tile.position }),
+        };
+    }
+
+    glyph_from_tile_type(tile.ttype)
+        .ok_or(SerializerError::UnsupportedTileType{ ttype: tile.ttype, position: tile.position })
+}
+
+fn index(pos: &Position, x_size: i32) -> usize {
+    (pos.y * x_size + pos.x) as usize
+}
+
+#[cfg(test)]
+mod test {
+    use failure::Error;
+    use rand::{ Rng, SeedableRng };
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+
+    use std::fs;
+    use std::ffi::OsStr;
+
+    use super::super::{ Board, ETileType, Tile, Position, EDirection, DEFAULT_BOARDS_DIRECTORY, TILE_TYPE_GLYPHS, parse_board, load_board_by_name_from_file, tile_type_from_glyph };
+    use super::{ serialize_board, SerializerError };
+
+    #[test]
+    fn test_serialize_board() -> Result<(), Error> {
+        let content = [
+            " -     ",
+            " o o o ",
+            "   - - ",
+            " o|1   ",
+            "   -   ",
+            " o|o 2|",
+            "   -   ",
+        ].join("\n");
+        let board = parse_board(content.clone())?;
+        assert_eq!(serialize_board(&board)?, content);
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_walls_stored_on_either_side() -> Result<(), Error> {
+        let tile = |x, y, walls| Tile {
+            position: Position{ x, y },
+            ttype: ETileType::Regular,
+            walls,
+            start_position_id: None,
+        };
+        let board = Board {
+            tiles: vec![
+                tile(0, 0, vec![EDirection::EAST, EDirection::SOUTH]),
+                tile(1, 0, vec![EDirection::WEST]),
+                tile(0, 1, vec![EDirection::NORTH]),
+                tile(1, 1, vec![]),
+            ],
+            size_x: 2,
+            size_y: 2,
+        };
+        let expected = [
+            "     ",
+            " o|o ",
+            " -   ",
+            " o o ",
+            "     ",
+        ].join("\n");
+        assert_eq!(serialize_board(&board)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_unsupported() {
+        let board = Board {
+            tiles: vec![Tile {
+                position: Position{ x: 0, y: 0 },
+                ttype: ETileType::Regular,
+                walls: vec![],
+                start_position_id: Some(12),
+            }],
+            size_x: 1,
+            size_y: 1,
+        };
+        match serialize_board(&board) {
+            Err(SerializerError::UnsupportedStartPosition{ id: 12, .. }) => (),
+            other => panic!("Expected UnsupportedStartPosition, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_round_trip_all_board_files() -> Result<(), Error> {
+        for entry in fs::read_dir(DEFAULT_BOARDS_DIRECTORY)? {
+            let path = entry?.path();
+            if path.extension() != Some(OsStr::new("brd")) {
+                continue;
+            }
+            let board = load_board_by_name_from_file(&path)?;
+            let serialized = serialize_board(&board)?;
+            let parsed = parse_board(serialized)?;
+            assert_eq!(board, parsed, "round trip of {}", path.display());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_trip_random_boards() -> Result<(), Error> {
+        let glyphs: Vec<char> = TILE_TYPE_GLYPHS.chars().collect();
+        // One seed per board, so a failing board can be generated again
+        for seed in 0..200 {
+            let board = random_board(&mut StdRng::seed_from_u64(seed), &glyphs);
+            let serialized = serialize_board(&board)?;
+            let parsed = parse_board(serialized.clone())?;
+            assert_eq!(board, parsed, "round trip of board {}:\n{}", seed, serialized);
+        }
+        Ok(())
+    }
+
+    /// Generates a board with walls stored in the same places parse_board puts them
+    fn random_board(rng: &mut StdRng, glyphs: &[char]) -> Board {
+        let size_x = rng.gen_range(1..12);
+        let size_y = rng.gen_range(1..12);
+        let mut start_ids: Vec<u32> = (0..10).collect();
+        start_ids.shuffle(rng);
+
+        let mut tiles = vec![];
+        for y in 0..size_y {
+            for x in 0..size_x {
+                let mut walls = vec![];
+                if rng.gen_bool(0.2) {
+                    walls.push(EDirection::WEST);
+                }
+                if x == size_x - 1 && rng.gen_bool(0.2) {
+                    walls.push(EDirection::EAST);
+                }
+                if rng.gen_bool(0.2) {
+                    walls.push(EDirection::NORTH);
+                }
+                if y == size_y - 1 && rng.gen_bool(0.2) {
+                    walls.push(EDirection::SOUTH);
+                }
+
+                let (ttype, start_position_id) = if rng.gen_bool(0.1) && !start_ids.is_empty() {
+                    (ETileType::Regular, start_ids.pop())
+                } else {
+                    (tile_type_from_glyph(*glyphs.choose(rng).unwrap()).unwrap(), None)
+                };
+                tiles.push(Tile {
+                    position: Position{ x, y },
+                    ttype,
+                    walls,
+                    start_position_id,
+                });
+            }
+        }
+        Board {
+            tiles,
+            size_x,
+            size_y,
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,346 @@
+#![allow(clippy::trivially_copy_pass_by_ref)]
+use std::fmt;
+
+use super::{ Board, EConnection, EDirection, ETileType, Position, StartPositionID, MAX_PLAYER_COUNT };
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EIssueSeverity {
+    /// The board is playable, but probably not what the designer intended
+    Warning,
+    /// The board can't be played
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EBoardIssue {
+    ConveyorIntoWall {
+        out: EDirection,
+    },
+    UnfedConveyorInput {
+        input: EDirection,
+    },
+    StartPositionOnNoTile {
+        start_position_id: StartPositionID,
+    },
+    DoubleWall {
+        direction: EDirection,
+    },
+    NotEnoughStartPositions {
+        player_count: usize,
+        start_position_count: usize,
+    },
+    /// Fewer start positions than MAX_PLAYER_COUNT: the board can't seat every game
+    FewStartPositions {
+        start_position_count: usize,
+    },
+    UnpairedPortal {
+        label: char,
+        count: usize,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoardIssue {
+    pub severity: EIssueSeverity,
+    pub position: Option<Position>,
+    pub issue: EBoardIssue,
+}
+
+impl BoardIssue {
+    fn new(issue: EBoardIssue, position: Option<Position>) -> BoardIssue {
+        let severity = match issue {
+            EBoardIssue::ConveyorIntoWall{ .. } => EIssueSeverity::Warning,
+            EBoardIssue::UnfedConveyorInput{ .. } => EIssueSeverity::Warning,
+            EBoardIssue::DoubleWall{ .. } => EIssueSeverity::Warning,
+            EBoardIssue::FewStartPositions{ .. } => EIssueSeverity::Warning,
+            EBoardIssue::StartPositionOnNoTile{ .. } => EIssueSeverity::Error,
+            EBoardIssue::NotEnoughStartPositions{ .. } => EIssueSeverity::Error,
+            EBoardIssue::UnpairedPortal{ .. } => EIssueSeverity::Error,
+        };
+        BoardIssue {
+            severity,
+            position,
+            issue,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == EIssueSeverity::Error
+    }
+}
+
+impl fmt::Display for BoardIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.issue {
+            EBoardIssue::ConveyorIntoWall{ out } => write!(f, "Conveyor leads into a wall ({:?})", out),
+            EBoardIssue::UnfedConveyorInput{ input } => write!(f, "Conveyor input ({:?}) is not fed by a neighboring conveyor", input),
+            EBoardIssue::StartPositionOnNoTile{ start_position_id } => write!(f, "Start position {} is not on a tile", start_position_id),
+            EBoardIssue::DoubleWall{ direction } => write!(f, "Wall ({:?}) is set on both sides", direction),
+            EBoardIssue::NotEnoughStartPositions{ player_count, start_position_count } => {
+                write!(f, "Only {} start position(s) for {} players", start_position_count, player_count)
+            },
+            EBoardIssue::FewStartPositions{ start_position_count } => {
+                write!(f, "Only {} start position(s), games of up to {} players are not possible", start_position_count, MAX_PLAYER_COUNT)
+            },
+            EBoardIssue::UnpairedPortal{ label, count } => write!(f, "Portal '{}' needs exactly one partner, found {} portal(s)", label, count),
+        }?;
+        if let Some(position) = self.position {
+            write!(f, " at [{}, {}]", position.x, position.y)?;
+        }
+        Ok(())
+    }
+}
+
+impl Board {
+    /// Checks the board for semantic problems the parser can't catch. Errors make the board unplayable. Run on every
+    /// board of the catalogue, so it warns about boards that can't seat MAX_PLAYER_COUNT players.
+    pub fn validate(&self) -> Vec<BoardIssue> {
+        let mut issues = self.validate_tiles();
+        let start_position_count = self.start_position_count();
+        if start_position_count < MAX_PLAYER_COUNT {
+            issues.push(BoardIssue::new(EBoardIssue::FewStartPositions{ start_position_count }, None));
+        }
+        issues
+    }
+
+    /// Like validate, but checks whether the board can seat the given number of players instead
+    pub fn validate_for_players(&self, player_count: usize) -> Vec<BoardIssue> {
+        let mut issues = self.validate_tiles();
+        let start_position_count = self.start_position_count();
+        if start_position_count < player_count {
+            issues.push(BoardIssue::new(EBoardIssue::NotEnoughStartPositions{ player_count, start_position_count }, None));
+        }
+        issues
+    }
+
+    fn validate_tiles(&self) -> Vec<BoardIssue> {
+        let mut issues = vec![];
+        for tile in &self.tiles {
+            let position = tile.position;
+            let mut push = |issue: EBoardIssue| issues.push(BoardIssue::new(issue, Some(position)));
+
+            if let Some(start_position_id) = tile.start_position_id {
+                if tile.ttype == ETileType::NoTile {
+                    push(EBoardIssue::StartPositionOnNoTile{ start_position_id });
+                }
+            }
+
+            // Only look east and south so each edge is checked once
+            for direction in &[EDirection::EAST, EDirection::SOUTH] {
+                if tile.walls.contains(direction) && self.has_wall_towards(&position, *direction) {
+                    push(EBoardIssue::DoubleWall{ direction: *direction });
+                }
+            }
+
+            let (out, inputs) = match tile.ttype {
+                ETileType::Conveyor2{ out, input, .. } => (out, vec![input]),
+                ETileType::Conveyor3{ out, inputs, .. } => (out, inputs.as_slice().to_vec()),
+                _ => continue,
+            };
+            if let Ok(EConnection::Walled) = self.get_neighbor_in(&position, out) {
+                push(EBoardIssue::ConveyorIntoWall{ out });
+            }
+            for input in inputs {
+                if !self.is_fed_from(&position, input) {
+                    push(EBoardIssue::UnfedConveyorInput{ input });
+                }
+            }
+        }
+
+        // Portals come in pairs
+        let mut labels: Vec<(char, Position, usize)> = vec![];
+        for tile in &self.tiles {
+            if let ETileType::Portal{ label } = tile.ttype {
+                match labels.iter_mut().find(|(l, _, _)| *l == label) {
+                    Some((_, _, count)) => *count += 1,
+                    None => labels.push((label, tile.position, 1)),
+                }
+            }
+        }
+        for (label, position, count) in labels {
+            if count != 2 {
+                issues.push(BoardIssue::new(EBoardIssue::UnpairedPortal{ label, count }, Some(position)));
+            }
+        }
+        issues
+    }
+
+    /// Whether the neighbor in the given direction has a wall facing back towards position
+    fn has_wall_towards(&self, position: &Position, direction: EDirection) -> bool {
+        self.get_tile(&position.neighbor_in(direction))
+            .map_or(false, |t| t.walls.contains(&direction.opposite()))
+    }
+
+    /// Whether the neighbor in the given direction is a conveyor that leads onto position
+    fn is_fed_from(&self, position: &Position, input: EDirection) -> bool {
+        let neighbor = match self.get_neighbor_in(position, input) {
+            Ok(EConnection::Free(neighbor)) => neighbor,
+            _ => return false,
+        };
+        match self.get_tile_type_at(&neighbor) {
+            Ok(ETileType::Conveyor2{ out, .. }) | Ok(ETileType::Conveyor3{ out, .. }) => out == input.opposite(),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use failure::Error;
+
+    use std::fs;
+    use std::ffi::OsStr;
+
+    use super::super::{ Board, EDirection, Position, DEFAULT_BOARDS_DIRECTORY, parse_board, load_board_by_name_from_file };
+    use super::{ BoardIssue, EBoardIssue, EIssueSeverity };
+
+    /// Without the check of the start position count, see test_not_enough_start_positions
+    fn issues_of(rows: &[&str]) -> Result<Vec<BoardIssue>, Error> {
+        let board = parse_board(rows.join("\n"))?;
+        Ok(board.validate_tiles())
+    }
+
+    #[test]
+    fn test_valid_board() -> Result<(), Error> {
+        let issues = issues_of(&[
+            "       ",
+            " → → ┒ ",
+            "       ",
+            " 1 2 ↓ ",
+            "       ",
+        ])?;
+        // The first conveyor is not fed, which is only a warning
+        assert_eq!(issues, vec![
+            BoardIssue {
+                severity: EIssueSeverity::Warning,
+                position: Some(Position::new(0, 0)),
+                issue: EBoardIssue::UnfedConveyorInput{ input: EDirection::WEST },
+            },
+        ]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_conveyor_into_wall() -> Result<(), Error> {
+        let issues = issues_of(&[
+            "     ",
+            " ┕ →|",
+            "     ",
+        ])?;
+        assert!(issues.contains(&BoardIssue {
+            severity: EIssueSeverity::Warning,
+            position: Some(Position::new(1, 0)),
+            issue: EBoardIssue::ConveyorIntoWall{ out: EDirection::EAST },
+        }), "conveyor into wall: {:?}", issues);
+        Ok(())
+    }
+
+    #[test]
+    fn test_double_wall() -> Result<(), Error> {
+        let mut board = parse_board([
+            "     ",
+            " o|o ",
+            "     ",
+        ].join("\n"))?;
+        board.tiles[0].walls.push(EDirection::EAST);
+
+        assert_eq!(board.validate_tiles(), vec![
+            BoardIssue {
+                severity: EIssueSeverity::Warning,
+                position: Some(Position::new(0, 0)),
+                issue: EBoardIssue::DoubleWall{ direction: EDirection::EAST },
+            },
+        ]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_start_position_on_no_tile() -> Result<(), Error> {
+        let mut board = parse_board([
+            "     ",
+            " o 1 ",
+            "     ",
+        ].join("\n"))?;
+        board.tiles[1].ttype = super::super::ETileType::NoTile;
+
+        let issues = board.validate_tiles();
+        assert_eq!(issues.len(), 1, "one issue");
+        assert!(issues[0].is_error(), "is error");
+        assert_eq!(issues[0].issue, EBoardIssue::StartPositionOnNoTile{ start_position_id: 1 });
+        Ok(())
+    }
+
+    #[test]
+    fn test_unpaired_portal() -> Result<(), Error> {
+        let issues = issues_of(&[
+            "       ",
+            " a b a ",
+            "       ",
+            " c c c ",
+            "       ",
+        ])?;
+        assert_eq!(issues, vec![
+            BoardIssue {
+                severity: EIssueSeverity::Error,
+                position: Some(Position::new(1, 0)),
+                issue: EBoardIssue::UnpairedPortal{ label: 'b', count: 1 },
+            },
+            BoardIssue {
+                severity: EIssueSeverity::Error,
+                position: Some(Position::new(0, 1)),
+                issue: EBoardIssue::UnpairedPortal{ label: 'c', count: 3 },
+            },
+        ]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_not_enough_start_positions() -> Result<(), Error> {
+        let board = parse_board([
+            "     ",
+            " o 1 ",
+            "     ",
+        ].join("\n"))?;
+
+        assert!(board.validate_for_players(1).is_empty(), "enough for one player");
+        assert_eq!(board.validate_for_players(2), vec![
+            BoardIssue {
+                severity: EIssueSeverity::Error,
+                position: None,
+                issue: EBoardIssue::NotEnoughStartPositions{ player_count: 2, start_position_count: 1 },
+            },
+        ]);
+
+        // The catalogue only warns, as the board is fine for fewer players
+        assert_eq!(board.validate(), vec![
+            BoardIssue {
+                severity: EIssueSeverity::Warning,
+                position: None,
+                issue: EBoardIssue::FewStartPositions{ start_position_count: 1 },
+            },
+        ]);
+        let board = parse_board([
+            "                 ",
+            " 1 2 3 4 5 6 7 8 ",
+            "                 ",
+        ].join("\n"))?;
+        assert!(board.validate().is_empty(), "enough for MAX_PLAYER_COUNT players");
+        Ok(())
+    }
+
+    #[test]
+    fn test_board_files_have_no_errors() -> Result<(), Error> {
+        for entry in fs::read_dir(DEFAULT_BOARDS_DIRECTORY)? {
+            let path = entry?.path();
+            if path.extension() != Some(OsStr::new("brd")) {
+                continue;
+            }
+            let board: Board = load_board_by_name_from_file(&path)?;
+            let errors: Vec<BoardIssue> = board.validate().into_iter()
+                .filter(|i| i.is_error())
+                .collect();
+            assert!(errors.is_empty(), "{}: {:?}", path.display(), errors);
+        }
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -1,8 +1,66 @@
 use std::slice::Iter;
 use std::fmt;
 use std::collections::HashSet;
+use std::path::PathBuf;
+
+use failure::Fail;
 
 use crate::roborally::engine::register_engine::{ ESimpleMove, TMove };
+use super::{ CardDefinition, load_deck_definition, parse_deck_definition };
+
+#[derive(Debug, Fail)]
+pub enum DeckError {
+    #[fail(display = "Error while reading file: {}", msg)]
+    FileError {
+        msg: String,
+    },
+    #[fail(display = "Line {}: {}", line, msg)]
+    SyntaxError {
+        line: usize,
+        msg: String,
+    },
+    #[fail(display = "Not enough priorities for card '{}': needed {}, found {}", card, needed, found)]
+    NotEnoughPriorities {
+        card: String,
+        needed: u32,
+        found: u32,
+    },
+    #[fail(display = "The deck holds no cards")]
+    EmptyDeck,
+    #[fail(display = "Invalid deck name '{}': only letters, digits, '-' and '_' are allowed", name)]
+    InvalidDeckName {
+        name: String,
+    },
+    #[fail(display = "There is no deck named '{}'", name)]
+    UnknownDeck {
+        name: String,
+    },
+    #[fail(display = "The deck holds {} cards, {} players need at least {}", found, player_count, needed)]
+    NotEnoughCards {
+        player_count: usize,
+        needed: u32,
+        found: u32,
+    },
+}
+
+/// How priorities are assigned to the cards of the standard deck. Custom priorities are not a scheme of their own:
+/// write a deck file (see deck_parser) which lists them and set ProgramCardDeckConfig::deck_file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EPriorityScheme {
+    /// Unique random priorities from 1 to 1000
+    Random,
+    /// The fixed priorities of the official game, as listed in OFFICIAL_DECK
+    Official,
+}
+
+impl Default for EPriorityScheme {
+    fn default() -> EPriorityScheme {
+        EPriorityScheme::Random
+    }
+}
+
+/// The deck of the official game (data/decks/official.deck), see deck_parser for the format
+pub const OFFICIAL_DECK: &str = include_str!("../../../data/decks/official.deck");
 
 #[derive(Debug)]
 pub struct ProgramCardDeckConfig {
@@ -13,12 +71,30 @@ pub struct ProgramCardDeckConfig {
     pub count_turn_right: u32,
     pub count_turn_left: u32,
     pub count_uturn: u32,
+    pub priority_scheme: EPriorityScheme,
+    /// A deck definition file (see deck_parser) which replaces the standard deck: counts and priority scheme are
+    /// ignored then
+    pub deck_file: Option<PathBuf>,
 }
 
 impl ProgramCardDeckConfig {
     pub fn card_count(&self) -> u32 {
         self.count_1_move + self.count_2_move + self.count_3_move + self.count_back_up + self.count_turn_left + self.count_turn_right + self.count_uturn
     }
+
+    /// (count, moves) for each card type, in the order they are added to the deck
+    fn card_types(&self) -> Vec<(u32, Vec<ESimpleMove>)> {
+        use ESimpleMove::*;
+        vec![
+            (self.count_1_move, vec![Forward]),
+            (self.count_2_move, vec![Forward, Forward]),
+            (self.count_3_move, vec![Forward, Forward, Forward]),
+            (self.count_back_up, vec![Backward]),
+            (self.count_turn_left, vec![TurnLeft]),
+            (self.count_turn_right, vec![TurnRight]),
+            (self.count_uturn, vec![UTurn]),
+        ]
+    }
 }
 
 impl Default for ProgramCardDeckConfig {
@@ -31,10 +107,16 @@ impl Default for ProgramCardDeckConfig {
             count_turn_left: 18,
             count_turn_right: 18,
             count_uturn: 6,
+            priority_scheme: EPriorityScheme::default(),
+            deck_file: None,
         }
     }
 }
 
+/// The priorities per card kind
+type PriorityTable = Vec<(ECardKind, Vec<u32>)>;
+
+#[derive(Default)]
 pub struct ProgramCardDeckGenerator {
     rng: rand::rngs::ThreadRng,
 }
@@ -46,9 +128,46 @@ impl ProgramCardDeckGenerator {
         }
     }
 
-    pub fn generate_program_deck(&mut self, config: &ProgramCardDeckConfig) -> ProgramCardDeck {
-        let mut cards = Vec::with_capacity(config.card_count() as usize);
+    pub fn generate_program_deck(&mut self, config: &ProgramCardDeckConfig) -> Result<ProgramCardDeck, DeckError> {
+        let definitions = match &config.deck_file {
+            Some(path) => load_deck_definition(path)?,
+            None => self.standard_deck_definition(config)?,
+        };
+
+        let mut cards = vec![];
+        for definition in definitions {
+            for priority in &definition.priorities[..definition.count as usize] {
+                cards.push(MoveCard::new_with_kind(cards.len() as u32, *priority, definition.kind.clone(), &definition.moves));
+            }
+        }
+        Ok(ProgramCardDeck { cards })
+    }
+
+    /// The standard cards in the numbers given by config, with priorities according to its priority scheme
+    fn standard_deck_definition(&mut self, config: &ProgramCardDeckConfig) -> Result<Vec<CardDefinition>, DeckError> {
+        let table: PriorityTable = match config.priority_scheme {
+            EPriorityScheme::Random => self.random_priority_table(config),
+            EPriorityScheme::Official => parse_deck_definition(OFFICIAL_DECK)?.into_iter()
+                .map(|d| (d.kind, d.priorities))
+                .collect(),
+        };
 
+        let mut definitions = vec![];
+        for (count, moves) in config.card_types() {
+            let kind = ECardKind::from_moves(&moves);
+            let priorities = table.iter()
+                .find(|(k, _)| *k == kind)
+                .map(|(_, priorities)| priorities.clone())
+                .unwrap_or_default();
+            if (priorities.len() as u32) < count {
+                return Err(DeckError::NotEnoughPriorities{ card: kind.to_string(), needed: count, found: priorities.len() as u32 });
+            }
+            definitions.push(CardDefinition{ kind, moves, count, priorities });
+        }
+        Ok(definitions)
+    }
+
+    fn random_priority_table(&mut self, config: &ProgramCardDeckConfig) -> PriorityTable {
         let mut priorities: HashSet<u32> = HashSet::new();
         for _ in 0..config.card_count() {
             loop {
@@ -61,32 +180,9 @@ impl ProgramCardDeckGenerator {
         }
         let mut it = priorities.into_iter();
 
-        let add_move_card = |cards: &mut Vec<MoveCard>, it: &mut std::collections::hash_set::IntoIter<u32>, moves: &[ESimpleMove]| {
-            cards.push(MoveCard::new_from_moves(cards.len() as u32, it.next().unwrap(), moves));
-        };
-
-        for _ in 0..config.count_1_move {
-            add_move_card(&mut cards, &mut it, &[ESimpleMove::Forward]);
-        }
-        for _ in 0..config.count_2_move {
-            add_move_card(&mut cards, &mut it, &[ESimpleMove::Forward, ESimpleMove::Forward]);
-        }
-        for _ in 0..config.count_3_move {
-            add_move_card(&mut cards, &mut it, &[ESimpleMove::Forward, ESimpleMove::Forward, ESimpleMove::Forward]);
-        }
-        for _ in 0..config.count_back_up {
-            add_move_card(&mut cards, &mut it, &[ESimpleMove::Backward]);
-        }
-        for _ in 0..config.count_turn_left {
-            add_move_card(&mut cards, &mut it, &[ESimpleMove::TurnLeft]);
-        }
-        for _ in 0..config.count_turn_right {
-            add_move_card(&mut cards, &mut it, &[ESimpleMove::TurnRight]);
-        }
-        for _ in 0..config.count_uturn {
-            add_move_card(&mut cards, &mut it, &[ESimpleMove::UTurn]);
-        }
-        ProgramCardDeck { cards }
+        config.card_types().into_iter()
+            .map(|(count, moves)| (ECardKind::from_moves(&moves), it.by_ref().take(count as usize).collect()))
+            .collect()
     }
 }
 
@@ -116,10 +212,11 @@ impl ProgramCardDeck {
         }
     }
 
+    /// Takes amount cards, or all of them if the deck holds fewer
     pub fn take_random_cards(&self, amount: u32) -> (ProgramCardDeck, Vec<MoveCard>) {
         use rand::seq::index;
 
-        let amount = amount as usize;
+        let amount = (amount as usize).min(self.cards.len());
         let mut cards = self.cards.clone();
         let mut rng = rand::thread_rng();
         let mut indeces = index::sample(&mut rng, cards.len(), amount).into_vec();
@@ -137,16 +234,82 @@ impl ProgramCardDeck {
 
 pub type MoveCardID = u32;
 
+/// What a card does. Unlike id and priority, this is the same for all cards of a type in every deck.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ECardKind {
+    Move1,
+    Move2,
+    Move3,
+    BackUp,
+    TurnLeft,
+    TurnRight,
+    UTurn,
+    StepLeft,
+    StepRight,
+    /// Any other move chain (e.g., variant cards)
+    Custom {
+        name: String,
+    },
+}
+
+impl ECardKind {
+    /// The kind of a card with the given move chain. Chains which are no standard card are named after their moves.
+    pub fn from_moves(moves: &[ESimpleMove]) -> ECardKind {
+        use ESimpleMove::*;
+        match moves {
+            [Forward] => ECardKind::Move1,
+            [Forward, Forward] => ECardKind::Move2,
+            [Forward, Forward, Forward] => ECardKind::Move3,
+            [Backward] => ECardKind::BackUp,
+            [TurnLeft] => ECardKind::TurnLeft,
+            [TurnRight] => ECardKind::TurnRight,
+            [UTurn] => ECardKind::UTurn,
+            [StepLeft] => ECardKind::StepLeft,
+            [StepRight] => ECardKind::StepRight,
+            _ => {
+                let names: Vec<String> = moves.iter().map(|m| format!("{:?}", m)).collect();
+                ECardKind::Custom{ name: names.join(" + ") }
+            },
+        }
+    }
+}
+
+impl fmt::Display for ECardKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ECardKind::Move1 => "Move 1",
+            ECardKind::Move2 => "Move 2",
+            ECardKind::Move3 => "Move 3",
+            ECardKind::BackUp => "Back Up",
+            ECardKind::TurnLeft => "Turn Left",
+            ECardKind::TurnRight => "Turn Right",
+            ECardKind::UTurn => "U-Turn",
+            ECardKind::StepLeft => "Step Left",
+            ECardKind::StepRight => "Step Right",
+            ECardKind::Custom{ name } => name,
+        };
+        write!(f, "{}", name)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct MoveCard {
     pub id: MoveCardID,
     pub priority: u32,
+    pub kind: ECardKind,
     pub tmove: Box<dyn TMove + Send>,
 }
 
+/// E.g. "Move 2 (#12, priority 670)"
+impl fmt::Display for MoveCard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (#{}, priority {})", self.kind, self.id, self.priority)
+    }
+}
+
 impl fmt::Debug for (dyn TMove + Send + 'static) {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "TMove")
+        f.debug_list().entries(self.iter()).finish()
     }
 }
 impl Clone for Box<dyn TMove + Send> {
@@ -157,13 +320,24 @@ impl Clone for Box<dyn TMove + Send> {
 
 impl MoveCard {
     pub fn new(id: MoveCardID, priority: u32, tmove: Box<dyn TMove + Send>) -> MoveCard {
+        let moves: Vec<ESimpleMove> = tmove.iter().cloned().collect();
         MoveCard {
             id,
             priority,
+            kind: ECardKind::from_moves(&moves),
             tmove,
         }
     }
 
+    pub fn new_with_kind(id: MoveCardID, priority: u32, kind: ECardKind, moves: &[ESimpleMove]) -> MoveCard {
+        MoveCard {
+            id,
+            priority,
+            kind,
+            tmove: SimpleMove::new(moves),
+        }
+    }
+
     pub fn new_from_moves(id: MoveCardID, priority: u32, moves: &[ESimpleMove]) -> MoveCard {
         let tmove = SimpleMove::new(moves);
         MoveCard::new(id, priority, tmove)
@@ -190,4 +364,91 @@ impl TMove for SimpleMove {
     fn box_clone(&self) -> Box<dyn TMove + Send> {
         Box::new((*self).clone())
     }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn priorities_by_moves(deck: &ProgramCardDeck, moves: &[ESimpleMove]) -> Vec<u32> {
+        deck.cards.iter()
+            .filter(|c| c.tmove.iter().eq(moves.iter()))
+            .map(|c| c.priority)
+            .collect()
+    }
+
+    #[test]
+    fn test_card_kinds() {
+        use ESimpleMove::*;
+        let cases: [(&[ESimpleMove], ECardKind, &str); 5] = [
+            (&[Forward, Forward], ECardKind::Move2, "Move 2 (#7, priority 670)"),
+            (&[Backward], ECardKind::BackUp, "Back Up (#7, priority 670)"),
+            (&[UTurn], ECardKind::UTurn, "U-Turn (#7, priority 670)"),
+            (&[StepLeft], ECardKind::StepLeft, "Step Left (#7, priority 670)"),
+            (&[Forward, TurnLeft], ECardKind::Custom{ name: String::from("Forward + TurnLeft") }, "Forward + TurnLeft (#7, priority 670)"),
+        ];
+        for (moves, kind, display) in &cases {
+            let card = MoveCard::new_from_moves(7, 670, moves);
+            assert_eq!(&card.kind, kind, "kind of {:?}", moves);
+            assert_eq!(&format!("{}", card), display, "display of {:?}", moves);
+        }
+
+        let debug = format!("{:?}", MoveCard::new_from_moves(7, 670, &[Forward, TurnLeft]));
+        assert!(debug.contains("[Forward, TurnLeft]"), "Debug prints the moves: {}", debug);
+    }
+
+    #[test]
+    fn test_take_random_cards() {
+        let deck = ProgramCardDeck {
+            cards: (0..3).map(|id| MoveCard::new_from_moves(id, id, &[])).collect(),
+        };
+        let (rest, taken) = deck.take_random_cards(2);
+        assert_eq!((rest.cards.len(), taken.len()), (1, 2), "some cards");
+        let (rest, taken) = rest.take_random_cards(2);
+        assert_eq!((rest.cards.len(), taken.len()), (0, 1), "more cards than left");
+    }
+
+    #[test]
+    fn test_random_priorities() -> Result<(), DeckError> {
+        let config = ProgramCardDeckConfig::default();
+        let deck = ProgramCardDeckGenerator::new().generate_program_deck(&config)?;
+        let priorities: HashSet<u32> = deck.cards.iter().map(|c| c.priority).collect();
+        assert_eq!(deck.cards.len() as u32, config.card_count(), "card count");
+        assert_eq!(priorities.len(), deck.cards.len(), "unique priorities");
+        assert!(priorities.iter().all(|p| (1..=1000).contains(p)), "priorities within 1..1000");
+        Ok(())
+    }
+
+    #[test]
+    fn test_official_priorities() -> Result<(), DeckError> {
+        use ESimpleMove::*;
+        let config = ProgramCardDeckConfig {
+            priority_scheme: EPriorityScheme::Official,
+            ..ProgramCardDeckConfig::default()
+        };
+        let deck = ProgramCardDeckGenerator::new().generate_program_deck(&config)?;
+        let cases: [(&[ESimpleMove], Vec<u32>); 7] = [
+            (&[UTurn], (10..=60).step_by(10).collect()),
+            (&[TurnLeft], (70..=410).step_by(20).collect()),
+            (&[TurnRight], (80..=420).step_by(20).collect()),
+            (&[Backward], (430..=480).step_by(10).collect()),
+            (&[Forward], (490..=660).step_by(10).collect()),
+            (&[Forward, Forward], (670..=780).step_by(10).collect()),
+            (&[Forward, Forward, Forward], (790..=840).step_by(10).collect()),
+        ];
+        for (moves, expected) in &cases {
+            assert_eq!(&priorities_by_moves(&deck, moves), expected, "priorities of {:?}", moves);
+        }
+        assert_eq!(deck.cards.len(), 84, "card count");
+
+        let config = ProgramCardDeckConfig {
+            count_uturn: 7,
+            ..config
+        };
+        match ProgramCardDeckGenerator::new().generate_program_deck(&config) {
+            Err(DeckError::NotEnoughPriorities{ card, needed: 7, found: 6 }) => assert_eq!(card, "U-Turn"),
+            other => panic!("Expected NotEnoughPriorities, got {:?}", other.map(|d| d.cards.len())),
+        }
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -0,0 +1,127 @@
+//! Dealing program cards and locking registers, as the rulebook has it:
+//!  - a robot is dealt 9 cards, minus one for each damage token it has
+//!  - starting with 5 damage tokens, registers get locked from the last one down: 5 damage locks register 5,
+//!    6 damage registers 4 and 5, ..., 9 damage all of them
+//!  - a locked register keeps its card, which is carried over into the next round. All other cards (those from unlocked
+//!    registers and the ones left in hand) are discarded back into the deck during cleanup.
+use super::*;
+
+/// Damage tokens a robot can take before its registers start to lock
+const UNLOCKED_DAMAGE_TOKENS: u32 = MAX_DAMAGE_TOKENS - REGISTER_COUNT as u32;
+
+/// The number of program cards dealt to a robot with the given damage
+pub fn hand_size(damage: u32) -> u32 {
+    MAX_DAMAGE_TOKENS.saturating_sub(damage)
+}
+
+/// The number of registers (counted from the last one) locked by the given damage
+pub fn locked_register_count(damage: u32) -> usize {
+    (damage.saturating_sub(UNLOCKED_DAMAGE_TOKENS) as usize).min(REGISTER_COUNT)
+}
+
+/// The number of cards needed to deal a full hand to each player
+pub fn min_deck_size(player_count: usize) -> u32 {
+    player_count as u32 * hand_size(0)
+}
+
+/// Whether the register with the given (0-based) index is locked by the given damage
+pub fn is_register_locked(register_index: usize, damage: u32) -> bool {
+    register_index + locked_register_count(damage) >= REGISTER_COUNT
+}
+
+impl State {
+    /// Puts the cards in hand and those in unlocked registers of all players back into the deck
+    pub fn discard_program_cards(&self) -> Result<Box<State>, StateError> {
+        let mut state = Box::from(self.clone());
+        for player in self.all_players() {
+            let (mut cards, mut new_player) = player.take_program_cards_from_unlocked_registers();
+            cards.append(&mut new_player.program_card_deck);
+            state = state.set_deck(state.deck.add_cards(cards));
+            state = state.update_player(new_player)?;
+        }
+        Ok(state)
+    }
+
+    /// Deals a fresh hand to all active players, its size depending on their robot's damage
+    pub fn deal_program_cards(&self) -> Result<Box<State>, StateError> {
+        let mut state = Box::from(self.clone());
+        for player in self.active_players() {
+            let (deck, cards) = state.deck.take_random_cards(hand_size(player.robot.damage));
+            state = state.set_deck(deck);
+            state = state.update_player(player.set_program_card_deck(cards))?;
+        }
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use failure::Error;
+
+    use super::*;
+
+    #[test]
+    fn test_hand_size_and_locked_registers() {
+        let cases = [
+            // (damage, hand size, locked registers)
+            (0, 9, "-----"),
+            (1, 8, "-----"),
+            (2, 7, "-----"),
+            (3, 6, "-----"),
+            (4, 5, "-----"),
+            (5, 4, "----L"),
+            (6, 3, "---LL"),
+            (7, 2, "--LLL"),
+            (8, 1, "-LLLL"),
+            (9, 0, "LLLLL"),
+            (10, 0, "LLLLL"),     // destroyed
+        ];
+        for (damage, expected_hand_size, expected_locks) in &cases {
+            assert_eq!(hand_size(*damage), *expected_hand_size, "hand size for damage {}", damage);
+            let locks: String = (0..REGISTER_COUNT)
+                .map(|i| if is_register_locked(i, *damage) { 'L' } else { '-' })
+                .collect();
+            assert_eq!(&locks, expected_locks, "locked registers for damage {}", damage);
+            assert_eq!(locked_register_count(*damage), locks.matches('L').count(), "locked register count for damage {}", damage);
+        }
+    }
+
+    fn create_state(damage: u32) -> Box<State> {
+        let robot = RobotBuilder::default().id(0).damage(damage).build().unwrap();
+        let mut player = Player::new_with_move(0, robot, MoveCard::new_from_moves(0, 0, &[]));
+        player.registers = (0..REGISTER_COUNT as u32)
+            .map(|i| Register{ move_card: Some(MoveCard::new_from_moves(100 + i, 10 + i, &[])), locked: false })
+            .collect();
+        let player = player.set_program_card_deck(vec![MoveCard::new_from_moves(200, 10, &[])]);
+        State::new_with_random_deck(Board::default(), vec![player])
+    }
+
+    fn register_card_ids(state: &State) -> Vec<Option<MoveCardID>> {
+        state.get_player_or_fail(0).unwrap().registers.iter()
+            .map(|r| r.move_card.as_ref().map(|c| c.id))
+            .collect()
+    }
+
+    #[test]
+    fn test_cleanup_and_deal() -> Result<(), Error> {
+        for damage in 0..=MAX_DAMAGE_TOKENS {
+            let state = create_state(damage);
+            let card_count = state.deck.cards.len() + REGISTER_COUNT + 1;
+
+            let state = state.lock_registers_according_to_damage().discard_program_cards()?;
+            let expected: Vec<Option<MoveCardID>> = (0..REGISTER_COUNT)
+                .map(|i| if is_register_locked(i, damage) { Some(100 + i as u32) } else { None })
+                .collect();
+            assert_eq!(register_card_ids(&state), expected, "locked registers keep their cards with damage {}", damage);
+            assert!(state.get_player_or_fail(0)?.program_card_deck.is_empty(), "hand discarded with damage {}", damage);
+            assert_eq!(state.deck.cards.len() + locked_register_count(damage), card_count, "cards returned to the deck with damage {}", damage);
+
+            let state = state.deal_program_cards()?;
+            let player = state.get_player_or_fail(0)?;
+            assert_eq!(player.program_card_deck.len(), hand_size(damage) as usize, "hand size with damage {}", damage);
+            assert_eq!(player.count_unlocked_registers(), REGISTER_COUNT - locked_register_count(damage), "unlocked registers with damage {}", damage);
+            assert!(player.program_card_deck.len() >= player.count_unlocked_registers(), "enough cards to program with damage {}", damage);
+        }
+        Ok(())
+    }
+}
\ No newline at end of file
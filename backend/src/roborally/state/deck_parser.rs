@@ -0,0 +1,232 @@
+//! Deck definition files (.deck) list the cards of a deck, one card type per line:
+//!
+//! ```text
+//! # <name> | <moves> | <count> | <priorities>
+//! Move 2 | Forward Forward | 12 | 670-780/10
+//! Sidestep + Forward | StepLeft Forward | 4 | 850 860 870 880
+//! ```
+//!
+//! - moves: the simple moves of the card, in order (Forward, Backward, StepLeft, StepRight, TurnLeft, TurnRight, UTurn)
+//! - priorities: single priorities or ranges `<from>-<to>/<step>` including both ends. The cards get the first `count`
+//!   of them.
+//!
+//! Cards whose moves are those of a standard card (e.g. Forward Forward) are of that kind, whatever their name. All
+//! others are custom cards named after the first column.
+use std::fs;
+use std::path::{ Path, PathBuf };
+
+use crate::roborally::engine::register_engine::ESimpleMove;
+use super::{ DeckError, ECardKind };
+
+pub const DEFAULT_DECKS_DIRECTORY: &str = "./data/decks";
+pub const DECK_FILE_EXTENSION: &str = "deck";
+
+/// One type of card in a deck
+#[derive(Debug, Clone, PartialEq)]
+pub struct CardDefinition {
+    pub kind: ECardKind,
+    pub moves: Vec<ESimpleMove>,
+    pub count: u32,
+    /// At least count priorities
+    pub priorities: Vec<u32>,
+}
+
+/// The path of the existing deck file with the given name in DEFAULT_DECKS_DIRECTORY
+pub fn deck_path(name: &str) -> Result<PathBuf, DeckError> {
+    let valid = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(DeckError::InvalidDeckName{ name: String::from(name) });
+    }
+    let path = PathBuf::from(DEFAULT_DECKS_DIRECTORY).join(format!("{}.{}", name, DECK_FILE_EXTENSION));
+    if !path.is_file() {
+        return Err(DeckError::UnknownDeck{ name: String::from(name) });
+    }
+    Ok(path)
+}
+
+pub fn load_deck_definition(path: &Path) -> Result<Vec<CardDefinition>, DeckError> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| DeckError::FileError{ msg: format!("{}: {}", path.display(), e) })?;
+    parse_deck_definition(&content)
+}
+
+pub fn parse_deck_definition(content: &str) -> Result<Vec<CardDefinition>, DeckError> {
+    let mut names: Vec<String> = vec![];
+    let mut definitions = vec![];
+    for (index, line) in content.lines().enumerate() {
+        let syntax_error = |msg: String| DeckError::SyntaxError{ line: index + 1, msg };
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let columns: Vec<&str> = line.split('|').map(|c| c.trim()).collect();
+        let (name, moves, count, priorities) = match columns.as_slice() {
+            [name, moves, count, priorities] => (*name, *moves, *count, *priorities),
+            _ => return Err(syntax_error(String::from("expected '<name> | <moves> | <count> | <priorities>'"))),
+        };
+
+        if name.is_empty() {
+            return Err(syntax_error(String::from("missing card name")));
+        }
+        if names.iter().any(|n| n == name) {
+            return Err(syntax_error(format!("duplicate card '{}'", name)));
+        }
+        names.push(String::from(name));
+
+        let moves = moves.split_whitespace()
+            .map(|m| parse_simple_move(m).ok_or_else(|| syntax_error(format!("unknown move '{}'", m))))
+            .collect::<Result<Vec<ESimpleMove>, DeckError>>()?;
+        if moves.is_empty() {
+            return Err(syntax_error(format!("card '{}' has no moves", name)));
+        }
+        let count: u32 = count.parse().map_err(|_| syntax_error(format!("invalid count '{}'", count)))?;
+        let mut priority_list = vec![];
+        for token in priorities.split_whitespace() {
+            let range = parse_priority_range(token).ok_or_else(|| syntax_error(format!("invalid priority '{}'", token)))?;
+            priority_list.extend(range);
+        }
+        if (priority_list.len() as u32) < count {
+            return Err(DeckError::NotEnoughPriorities{ card: String::from(name), needed: count, found: priority_list.len() as u32 });
+        }
+
+        let kind = match ECardKind::from_moves(&moves) {
+            ECardKind::Custom{ .. } => ECardKind::Custom{ name: String::from(name) },
+            kind => kind,
+        };
+        definitions.push(CardDefinition{ kind, moves, count, priorities: priority_list });
+    }
+
+    if definitions.iter().all(|d| d.count == 0) {
+        return Err(DeckError::EmptyDeck);
+    }
+    Ok(definitions)
+}
+
+/// `<priority>` or `<from>-<to>/<step>`
+fn parse_priority_range(token: &str) -> Option<Vec<u32>> {
+    let (range, step) = match token.find('/') {
+        Some(slash) => (&token[..slash], token[slash + 1..].parse::<usize>().ok().filter(|s| *s > 0)?),
+        None => (token, 1),
+    };
+    match range.find('-') {
+        Some(dash) => {
+            let from: u32 = range[..dash].parse().ok()?;
+            let to: u32 = range[dash + 1..].parse().ok()?;
+            Some((from..=to).step_by(step).collect())
+        },
+        None => Some(vec![range.parse().ok()?]),
+    }
+}
+
+fn parse_simple_move(token: &str) -> Option<ESimpleMove> {
+    use ESimpleMove::*;
+    match token {
+        "Forward" => Some(Forward),
+        "Backward" => Some(Backward),
+        "StepLeft" => Some(StepLeft),
+        "StepRight" => Some(StepRight),
+        "TurnLeft" => Some(TurnLeft),
+        "TurnRight" => Some(TurnRight),
+        "UTurn" => Some(UTurn),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::roborally::engine::register_engine::ESimpleMove;
+    use super::super::{ DeckError, ECardKind, EPriorityScheme, ProgramCardDeckConfig, ProgramCardDeckGenerator };
+    use super::{ deck_path, load_deck_definition, parse_deck_definition };
+
+    /// (kind, priority) of all cards, which is all that is left of the definition once dealt
+    fn cards_of(config: &ProgramCardDeckConfig) -> Result<Vec<(ECardKind, u32)>, DeckError> {
+        let deck = ProgramCardDeckGenerator::new().generate_program_deck(config)?;
+        let mut cards: Vec<(ECardKind, u32)> = deck.cards.into_iter().map(|c| (c.kind, c.priority)).collect();
+        cards.sort_by_key(|(_, priority)| *priority);
+        Ok(cards)
+    }
+
+    #[test]
+    fn test_official_deck_file() -> Result<(), DeckError> {
+        let official = ProgramCardDeckConfig {
+            priority_scheme: EPriorityScheme::Official,
+            ..ProgramCardDeckConfig::default()
+        };
+        let from_file = ProgramCardDeckConfig {
+            deck_file: Some(deck_path("official")?),
+            ..ProgramCardDeckConfig::default()
+        };
+        assert_eq!(cards_of(&from_file)?, cards_of(&official)?, "the default counts are those of official.deck");
+        Ok(())
+    }
+
+    #[test]
+    fn test_variant_cards() -> Result<(), DeckError> {
+        use ESimpleMove::*;
+        let definitions = load_deck_definition(&deck_path("variants")?)?;
+        let variant = definitions.iter()
+            .find(|d| d.kind == ECardKind::Custom{ name: String::from("Move 2 then Turn Left") })
+            .expect("variant card");
+        assert_eq!(variant.moves, vec![Forward, Forward, TurnLeft], "moves");
+
+        let config = ProgramCardDeckConfig {
+            deck_file: Some(deck_path("variants")?),
+            ..ProgramCardDeckConfig::default()
+        };
+        let deck = ProgramCardDeckGenerator::new().generate_program_deck(&config)?;
+        let expected_count: u32 = definitions.iter().map(|d| d.count).sum();
+        assert_eq!(deck.cards.len() as u32, expected_count, "card count");
+        let sidesteps: Vec<Vec<ESimpleMove>> = deck.cards.iter()
+            .filter(|c| c.kind == ECardKind::Custom{ name: String::from("Sidestep + Forward") })
+            .map(|c| c.tmove.iter().cloned().collect())
+            .collect();
+        assert!(!sidesteps.is_empty() && sidesteps.iter().all(|m| *m == vec![StepLeft, Forward]), "sidestep cards: {:?}", sidesteps);
+        Ok(())
+    }
+
+    #[test]
+    fn test_standard_moves_are_standard_kinds() -> Result<(), DeckError> {
+        let definitions = parse_deck_definition("Sprint | Forward Forward Forward | 1 | 900")?;
+        assert_eq!(definitions[0].kind, ECardKind::Move3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_deck_path() {
+        assert!(deck_path("variants").is_ok(), "plain name");
+        for name in &["", "../boards/official", "a/b", "variants.deck"] {
+            match deck_path(name) {
+                Err(DeckError::InvalidDeckName{ .. }) => (),
+                other => panic!("Expected InvalidDeckName for {:?}, got {:?}", name, other),
+            }
+        }
+        match deck_path("no-such-deck") {
+            Err(DeckError::UnknownDeck{ name }) => assert_eq!(name, "no-such-deck"),
+            other => panic!("Expected UnknownDeck, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_deck_definition_errors() {
+        let cases = [
+            ("Move 1 | Forward | 1", "Line 1"),
+            ("# comment\nMove 1 | Jump | 1 | 10", "Line 2"),
+            (" | Forward | 1 | 10", "Line 1"),
+            ("Move 1 | | 1 | 10", "Line 1"),
+            ("Move 1 | Forward | many | 10", "Line 1"),
+            ("Move 1 | Forward | 1 | 10\nMove 1 | Forward | 1 | 20", "Line 2"),
+            ("Move 1 | Forward | 1 | ten", "Line 1"),
+            ("Move 1 | Forward | 1 | 10-x/10", "Line 1"),
+            ("Move 1 | Forward | 1 | 10-60/0", "Line 1"),
+            ("Move 1 | Forward | 3 | 10-20/10", "Not enough priorities for card 'Move 1'"),
+            ("# nothing but comments", "The deck holds no cards"),
+            ("Move 1 | Forward | 0 | 10", "The deck holds no cards"),
+        ];
+        for (content, expected) in &cases {
+            match parse_deck_definition(content) {
+                Err(err) => assert!(format!("{}", err).starts_with(expected), "{:?}: {}", content, err),
+                Ok(_) => panic!("Expected an error for {:?}", content),
+            }
+        }
+    }
+}
\ No newline at end of file
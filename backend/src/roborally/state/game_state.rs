@@ -2,7 +2,8 @@ use super::{ ProgramCardDeckConfig, BoardConfig, PlayerConfig, State, StateError
 
 use failure::Error;
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
+#[cfg_attr(test, derive(Default))]
 pub struct GameConfig {
     pub deck_config: ProgramCardDeckConfig,
     pub board_config: BoardConfig,
@@ -33,7 +34,7 @@ impl GameState {
         })
     }
 
-    pub fn update_round(&mut self, round: Round) -> Result<(), Error> {
+    pub fn update_round(&mut self, round: Round) -> Result<(), StateError> {
         let i = self.rounds.iter().position(|r| r.id == round.id)
             .ok_or(StateError::RoundNotFound{ round_id: round.id })?;
         self.rounds[i] = round;
@@ -43,7 +44,8 @@ impl GameState {
     pub fn add_round(&mut self) -> &Round {
         let state = match self.rounds.last() {
             Some(r) => &r.state,
-            None => &self.initial_state,
+            // The first round starts with the robots on their start positions
+            None => &self.start_state,
         };
         let round = Round::new(self.rounds.len() as u32, state.clone());
         self.rounds.push(round);
@@ -116,6 +118,8 @@ pub struct Round {
     pub id: RoundID,
     pub phase: ERoundPhase,
     pub state: Box<State>,
+    /// The state at the end of the programming phase, with all registers programmed and the hands as they were left
+    pub programmed_state: Option<Box<State>>,
 }
 
 impl Round {
@@ -124,14 +128,20 @@ impl Round {
             id,
             phase: ERoundPhase::INITIALIZATION,
             state,
+            programmed_state: None,
         }
     }
 
     pub fn advance(&self, state: Box<State>, phase: ERoundPhase) -> Round {
+        let programmed_state = match (self.phase, phase) {
+            (ERoundPhase::PROGRAMMING, ERoundPhase::EXECUTION) => Some(state.clone()),
+            _ => self.programmed_state.clone(),
+        };
         Round {
             id: self.id,
             state,
-            phase
+            phase,
+            programmed_state,
         }
     }
 }
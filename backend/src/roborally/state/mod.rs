@@ -3,18 +3,30 @@
 use failure::Fail;
 
 mod cards;
+mod deck_parser;
 mod board;
 mod player;
 mod state;
 mod game_state;
+mod dealing;
 mod board_parser;
+mod board_serializer;
+mod board_validation;
+mod board_catalogue;
+mod renderer;
 
 pub use board::*;
 pub use cards::*;
+pub use deck_parser::*;
 pub use player::*;
 pub use state::*;
 pub use game_state::*;
+pub use dealing::*;
 pub use board_parser::*;
+pub use board_serializer::*;
+pub use board_validation::*;
+pub use board_catalogue::*;
+pub use renderer::*;
 
 #[derive(Debug, Fail)]
 pub enum StateError {
@@ -46,10 +58,12 @@ pub enum StateError {
     DoublePlayerInput {
         player_id: PlayerID,
     },
-    #[fail(display = "Invalid program card choice ({}) by player {}!", move_card_id, player_id)]
+    #[fail(display = "Invalid program card choice ({}) by player {}! Cards in hand: {}", move_card_id, player_id, hand)]
     InvalidProgramCardChoice {
         player_id: PlayerID,
         move_card_id: MoveCardID,
+        /// The cards the player could have chosen, for display
+        hand: String,
     },
     #[fail(display = "Position off board: {:?}", position)]
     PositionOffBoard {
@@ -59,4 +73,15 @@ pub enum StateError {
     StartPositionNotFoundID {
         start_position_id: StartPositionID,
     },
+    #[fail(display = "Board is not playable: {:?}", issues)]
+    InvalidBoard {
+        issues: Vec<BoardIssue>,
+    },
 }
+
+impl StateError {
+    /// The StateError at the root of err, if any, wherever it was raised
+    pub fn find_in(err: &dyn Fail) -> Option<&StateError> {
+        err.iter_chain().find_map(|cause| cause.downcast_ref::<StateError>())
+    }
+}
\ No newline at end of file
@@ -9,6 +9,8 @@ use super::StateError;
 pub type PlayerID = u32;
 
 pub const REGISTER_COUNT: usize = 5;
+/// The most players a game is meant for, as in the official game. Boards should have a start position for each.
+pub const MAX_PLAYER_COUNT: usize = 8;
 /// The maximum number of damage tokens that a robot can take and still function. Anything above destroxy the robot.
 pub const MAX_DAMAGE_TOKENS: u32 = 9;
 pub const DEFAULT_LIFE_TOKENS: u32 = 3;
@@ -16,19 +18,52 @@ pub const INITIAL_ROBOT_POSITION: Position = Position { x: -1, y: -1 };
 
 #[derive(Debug, Clone)]
 pub struct PlayerConfig {
-    player_count: usize,
+    /// One seat per player, ordered by player id
+    seats: Vec<ESeat>,
     // register_count: u32, TODO depends on damage tokens/max damage!
     life_tokens: u32,
 }
+impl PlayerConfig {
+    pub fn with_seats(seats: Vec<ESeat>) -> PlayerConfig {
+        PlayerConfig {
+            seats,
+            ..PlayerConfig::default()
+        }
+    }
+
+    pub fn player_count(&self) -> usize {
+        self.seats.len()
+    }
+}
+
 impl Default for PlayerConfig {
     fn default() -> Self {
         Self {
-            player_count: 2,
+            seats: vec![ESeat::Human, ESeat::Human],
             life_tokens: DEFAULT_LIFE_TOKENS,
         }
     }
 }
 
+/// Who is playing a seat: either a human (via player input) or a built-in bot
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ESeat {
+    Human,
+    Bot {
+        level: EBotLevel,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EBotLevel {
+    /// Plays random cards
+    Random,
+    /// Plays the cards that bring its robot closest to its next target (see TBotTarget)
+    Greedy,
+    /// Like Greedy, but avoids programs that kill its robot
+    AvoidDeath,
+}
+
 #[derive(Debug, Clone)]
 pub struct Player {
     pub id: PlayerID,
@@ -36,12 +71,13 @@ pub struct Player {
     pub registers: Vec<Register>,
     pub program_card_deck: Vec<MoveCard>,
     pub input_required: bool,
+    pub seat: ESeat,
 }
 
 impl Player {
     pub fn create_from(config: &PlayerConfig) -> Vec<Player> {
-        let mut players = Vec::with_capacity(config.player_count);
-        for id in 0..config.player_count {
+        let mut players = Vec::with_capacity(config.player_count());
+        for (id, seat) in config.seats.iter().enumerate() {
             players.push(Player {
                 id: id as u32,
                 robot: Robot {
@@ -51,10 +87,12 @@ impl Player {
                     powered_down: EPoweredDown::No,
                     position: INITIAL_ROBOT_POSITION,
                     direction: EDirection::SOUTH,
+                    is_virtual: false,
                 },
                 registers: (0..REGISTER_COUNT).map(|_| Register::default()).collect(),
                 program_card_deck: vec![],
                 input_required: false,
+                seat: *seat,
             });
         }
         players
@@ -74,6 +112,7 @@ impl Player {
             }],
             program_card_deck: vec![],
             input_required: false,
+            seat: ESeat::Human,
         }
     }
 
@@ -95,9 +134,13 @@ impl Player {
             });
         }
         let card_index = new_player.program_card_deck.iter().position(|c| c.id == move_card_id)
-            .ok_or(StateError::InvalidProgramCardChoice{
+            .ok_or_else(|| StateError::InvalidProgramCardChoice{
                 player_id: self.id,
                 move_card_id,
+                hand: self.program_card_deck.iter()
+                    .map(|c| format!("{}", c))
+                    .collect::<Vec<String>>()
+                    .join(", "),
             })?;
         
         register.move_card = Some(new_player.program_card_deck.remove(card_index));
@@ -111,7 +154,7 @@ impl Player {
             if r.locked {
                 continue;
             }
-            cards.push(r.move_card.take().unwrap());
+            cards.extend(r.move_card.take());
         }
         (cards, new_player)
     }
@@ -129,6 +172,19 @@ impl Player {
         //     .count()
     }
 
+    pub fn bot_level(&self) -> Option<EBotLevel> {
+        match self.seat {
+            ESeat::Human => None,
+            ESeat::Bot{ level } => Some(level),
+        }
+    }
+
+    /// Whether all registers hold a card
+    pub fn has_programmed_registers(&self) -> bool {
+        self.registers.iter()
+            .all(|r| r.move_card.is_some())
+    }
+
     /// All players which are still in the game (e.g., who either still have or will have a robot again)
     pub fn is_active(&self) -> bool {
         self.robot.life_tokens > 0 && !self.robot.is_destroyed()
@@ -146,6 +202,10 @@ pub struct Robot {
     pub position: Position,
     pub direction: EDirection,
     pub powered_down: EPoweredDown,
+    /// Virtual robots share a tile with another robot (e.g., having started on the same start position, see
+    /// GameEngine::set_start_position). They neither push nor get pushed until they are alone on their tile at the end
+    /// of a register.
+    pub is_virtual: bool,
 }
 
 impl Default for Robot {
@@ -157,6 +217,7 @@ impl Default for Robot {
             position: Position::default(),
             direction: EDirection::default(),
             powered_down: EPoweredDown::default(),
+            is_virtual: false,
         }
     }
 }
@@ -221,6 +282,13 @@ pub struct Register {
     pub locked: bool,
 }
 
+impl Register {
+    /// Whether the register keeps its card into the next round
+    pub fn is_carried_over(&self) -> bool {
+        self.locked && self.move_card.is_some()
+    }
+}
+
 pub struct PlayerIter {
     players: Vec<Player>,
 }
@@ -261,4 +329,25 @@ impl<'a> Iterator for PlayerIterator<'a> {
         self.next_index += 1;
         result
     }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::roborally::engine::register_engine::ESimpleMove;
+
+    #[test]
+    fn test_invalid_card_choice_lists_hand() {
+        let player = Player::new_with_move(0, Robot::default(), MoveCard::new_from_moves(0, 0, &[]));
+        let mut player = player.set_program_card_deck(vec![
+            MoveCard::new_from_moves(1, 790, &[ESimpleMove::Forward, ESimpleMove::Forward, ESimpleMove::Forward]),
+            MoveCard::new_from_moves(2, 10, &[ESimpleMove::UTurn]),
+        ]);
+        player.registers[0].move_card = None;
+
+        let err = player.choose_card(0, 3).unwrap_err();
+        assert_eq!(format!("{}", err), "Invalid program card choice (3) by player 0! Cards in hand: Move 3 (#1, priority 790), U-Turn (#2, priority 10)");
+        let player = player.choose_card(0, 2).unwrap();
+        assert_eq!(player.registers[0].move_card.as_ref().map(|c| c.kind.clone()), Some(ECardKind::UTurn));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,191 @@
+use std::fmt;
+
+use super::{ EDirection, Player, Position, State, Tile, glyph_from_tile_type };
+
+const DESTROYED_GLYPH: char = '✖';
+
+/// Draws a state as text: the board with the same glyphs as the .brd format, followed by a line per robot.
+/// Each tile is one character wider than in .brd so robots fit in as their direction arrow plus id (e.g. "▶1").
+/// Destroyed robots are marked with ✖ (and only drawn if their position is on the board).
+pub fn render_state(state: &State) -> String {
+    let board = &state.board;
+    let mut rows = Vec::with_capacity((board.size_y * 2 + 1) as usize);
+    for y in 0..board.size_y {
+        rows.push(render_horizontal_wall_row(state, y));
+        rows.push(render_tile_row(state, y));
+    }
+    rows.push(render_horizontal_wall_row(state, board.size_y));
+
+    for player in state.all_players() {
+        rows.push(render_robot_line(player));
+    }
+    rows.join("\n")
+}
+
+/// Renders both states next to each other. Lines that differ are marked with a '*'.
+pub fn render_side_by_side(before: &State, after: &State) -> String {
+    let before = render_state(before);
+    let after = render_state(after);
+    let before_lines: Vec<&str> = before.lines().collect();
+    let after_lines: Vec<&str> = after.lines().collect();
+    let width = before_lines.iter()
+        .map(|l| l.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut lines = vec![format!("  {:<width$} | {}", "before", "after", width = width)];
+    for i in 0..before_lines.len().max(after_lines.len()) {
+        let left = before_lines.get(i).cloned().unwrap_or("");
+        let right = after_lines.get(i).cloned().unwrap_or("");
+        let marker = if left == right { ' ' } else { '*' };
+        lines.push(format!("{} {:<width$} | {}", marker, left, right, width = width));
+    }
+    lines.join("\n")
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", render_state(self))
+    }
+}
+
+/// Walls between row y - 1 and row y
+fn render_horizontal_wall_row(state: &State, y: i32) -> String {
+    let mut row = String::new();
+    for x in 0..state.board.size_x {
+        let wall = state.board.has_wall(&Position{ x, y }, EDirection::NORTH)
+            || state.board.has_wall(&Position{ x, y: y - 1 }, EDirection::SOUTH);
+        row.push_str(if wall { " --" } else { "   " });
+    }
+    row.push(' ');
+    row
+}
+
+fn render_tile_row(state: &State, y: i32) -> String {
+    let mut row = String::new();
+    for x in 0..state.board.size_x {
+        let position = Position{ x, y };
+        row.push(if state.board.has_wall(&position, EDirection::WEST) { '|' } else { ' ' });
+
+        let robot_player = state.all_players()
+            .filter(|p| p.robot.position == position)
+            .last();
+        match (robot_player, state.board.get_tile(&position)) {
+            (Some(player), _) => {
+                row.push(robot_glyph(player));
+                row.push(id_glyph(player.id));
+            },
+            (None, Some(tile)) => {
+                row.push(tile_glyph(tile));
+                row.push(' ');
+            },
+            (None, None) => row.push_str("  "),
+        }
+    }
+    let last = Position{ x: state.board.size_x - 1, y };
+    row.push(if state.board.has_wall(&last, EDirection::EAST) { '|' } else { ' ' });
+    row
+}
+
+fn render_robot_line(player: &Player) -> String {
+    let robot = &player.robot;
+    let position = format!("[{}, {}]", robot.position.x, robot.position.y);
+    let status = if robot.is_destroyed() {
+        format!("destroyed at {}", position)
+    } else {
+        format!("{} facing {:?}", position, robot.direction)
+    };
+    format!("{}{} player {}: {}, damage {}, lives {}",
+        robot_glyph(player), id_glyph(player.id), player.id, status, robot.damage, robot.life_tokens)
+}
+
+fn robot_glyph(player: &Player) -> char {
+    if player.robot.is_destroyed() {
+        return DESTROYED_GLYPH;
+    }
+    match player.robot.direction {
+        EDirection::NORTH => '▲',
+        EDirection::EAST => '▶',
+        EDirection::SOUTH => '▼',
+        EDirection::WEST => '◀',
+    }
+}
+
+fn id_glyph(id: u32) -> char {
+    std::char::from_digit(id, 10).unwrap_or('+')
+}
+
+fn tile_glyph(tile: &Tile) -> char {
+    if let Some(id) = tile.start_position_id {
+        return std::char::from_digit(id, 10).unwrap_or('S');
+    }
+    glyph_from_tile_type(tile.ttype).unwrap_or('?')
+}
+
+#[cfg(test)]
+mod test {
+    use failure::Error;
+
+    use super::super::*;
+    use super::{ render_state, render_side_by_side };
+
+    fn create_state() -> Result<Box<State>, Error> {
+        let board = parse_board([
+            "       ",
+            " o|1 → ",
+            "   -   ",
+            " o o ↻ ",
+            "       ",
+        ].join("\n"))?;
+        let robot0 = RobotBuilder::default()
+            .id(0)
+            .position(Position::new(0, 1))
+            .direction(EDirection::EAST)
+            .build().unwrap();
+        let robot1 = RobotBuilder::default()
+            .id(1)
+            .position(Position::new(3, -1))
+            .direction(EDirection::NORTH)
+            .build().unwrap()
+            .die();
+        let players = vec![
+            Player::new_with_move(0, robot0, MoveCard::new_from_moves(0, 1, &[])),
+            Player::new_with_move(1, robot1, MoveCard::new_from_moves(1, 2, &[])),
+        ];
+        Ok(State::new_with_random_deck(board, players))
+    }
+
+    #[test]
+    fn test_render_state() -> Result<(), Error> {
+        let state = create_state()?;
+        let expected = [
+            "          ",
+            " o |1  →  ",
+            "    --    ",
+            " ▶0 o  ↻  ",
+            "          ",
+            "▶0 player 0: [0, 1] facing EAST, damage 0, lives 3",
+            "✖1 player 1: destroyed at [3, -1], damage 10, lives 3",
+        ].join("\n");
+        assert_eq!(render_state(&state), expected, "\n{}", render_state(&state));
+        assert_eq!(format!("{}", state), expected, "Display");
+        assert_eq!(format!("{:#?}", state), format!("{}\n", expected), "pretty Debug");
+        assert!(format!("{:?}", state).starts_with("State { board: Board {"), "Debug");
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_side_by_side() -> Result<(), Error> {
+        let before = create_state()?;
+        let robot = before.get_robot_by_player_id_or_fail(0)?.set_position(Position::new(1, 1));
+        let after = before.update_robot(robot)?;
+
+        let rendered = render_side_by_side(&before, &after);
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert!(lines[0].starts_with("  before"), "header: {}", lines[0]);
+        assert!(lines[1].starts_with("  "), "unchanged line: {}", lines[1]);
+        assert!(lines[4].starts_with("*  ▶0 o  ↻"), "changed line: {}", lines[4]);
+        assert!(lines[4].ends_with("|  o  ▶0 ↻  "), "changed line: {}", lines[4]);
+        Ok(())
+    }
+}
\ No newline at end of file
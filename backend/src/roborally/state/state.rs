@@ -2,25 +2,55 @@
 
 use failure::Error;
 
+use std::cmp::Reverse;
+use std::fmt;
 use std::sync::Arc;
 
 use super::*;
 
-#[derive(Debug, Clone, Default)]
+#[derive(Clone, Default)]
 pub struct State {
     pub board: Arc<Board>,
     players: Vec<Player>,
     pub deck: ProgramCardDeck,
 }
 
+/// `{:#?}` draws the state (also when pretty-printing e.g. a Round), `{:?}` lists the fields as derived Debug would
+impl fmt::Debug for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return writeln!(f, "{}", render_state(self));
+        }
+        f.debug_struct("State")
+            .field("board", &self.board)
+            .field("players", &self.players)
+            .field("deck", &self.deck)
+            .finish()
+    }
+}
+
 impl State {
     pub fn create_from(config: &GameConfig) -> Result<Box<State>, Error> {
-        let board = Board::create_from(&config.board_config)?;
+        let board = config.board_config.board.clone();
+        let issues: Vec<BoardIssue> = board.validate_for_players(config.player_config.player_count()).into_iter()
+            .filter(|i| i.is_error())
+            .collect();
+        if !issues.is_empty() {
+            return Err(StateError::InvalidBoard{ issues }.into());
+        }
+
         let mut gen = ProgramCardDeckGenerator::new();
+        let deck = gen.generate_program_deck(&config.deck_config)?;
+        let player_count = config.player_config.player_count();
+        let needed = min_deck_size(player_count);
+        if (deck.cards.len() as u32) < needed {
+            return Err(DeckError::NotEnoughCards{ player_count, needed, found: deck.cards.len() as u32 }.into());
+        }
+
         Ok(Box::from(State {
-            board: Arc::new(board),
+            board,
             players: Player::create_from(&config.player_config),
-            deck: gen.generate_program_deck(&config.deck_config),
+            deck,
         }))
     }
 
@@ -31,7 +61,7 @@ impl State {
         Box::from(State {
             board: Arc::new(board),
             players: players.into_iter().collect(),
-            deck: gen.generate_program_deck(&config),
+            deck: gen.generate_program_deck(&config).unwrap(),
         })
     }
 
@@ -94,8 +124,18 @@ impl State {
         }))
     }
 
-    pub fn update_player_fn<F>(&self, player_id: PlayerID, new_player_fn: F) -> Result<Box<State>, Error>
-        where F: Fn(&mut Player) -> Result<(), Error> {
+    pub fn update_player_fn<F>(&self, player_id: PlayerID, new_player_fn: F) -> Result<Box<State>, StateError>
+        where F: Fn(&mut Player) {
+        self.try_update_player_fn(player_id, |player| -> Result<(), StateError> {
+            new_player_fn(player);
+            Ok(())
+        })
+    }
+
+    /// Like update_player_fn, for changes that may fail
+    pub fn try_update_player_fn<F, E>(&self, player_id: PlayerID, new_player_fn: F) -> Result<Box<State>, E>
+        where F: Fn(&mut Player) -> Result<(), E>,
+              E: From<StateError> {
         let old_player_index = self.players.iter()
             .position(|p| p.id == player_id)
             .ok_or(StateError::PlayerNotFound{ player_id })?;
@@ -118,23 +158,52 @@ impl State {
         })
     }
 
+    /// The active robot at pos, destroyed robots have left the board (even if crushed where they stood)
     pub fn find_robot_at(&self, pos: &Position) -> Option<&Robot> {
-        self.players.iter()
+        self.active_players()
             .find(|p| p.robot.position == *pos)
             .map(|p| &p.robot)
     }
 
+    /// The active robot at pos that robot runs into (and pushes). Virtual robots don't interact with other robots.
+    pub fn find_obstructing_robot(&self, robot: &Robot, pos: &Position) -> Option<&Robot> {
+        if robot.is_virtual {
+            return None;
+        }
+        // A real robot may share its tile with virtual ones
+        self.active_players()
+            .find(|p| p.robot.position == *pos && !p.robot.is_virtual)
+            .map(|p| &p.robot)
+    }
+
+    /// Virtual robots which are alone on their tile become real
+    pub fn make_lone_virtual_robots_real(&self) -> Box<State> {
+        let occupied: Vec<Position> = self.active_players()
+            .map(|p| p.robot.position)
+            .collect();
+        let mut state = Box::from(self.clone());
+        for p in &mut state.players {
+            if p.is_active() && p.robot.is_virtual && occupied.iter().filter(|pos| **pos == p.robot.position).count() == 1 {
+                p.robot.is_virtual = false;
+            }
+        }
+        state
+    }
+
+    /// Locks and unlocks the registers of all players according to the damage of their robots. Cards stay where they are.
     pub fn lock_registers_according_to_damage(&self) -> Box<State> {
         let mut state = Box::from(self.clone());
         for p in &mut state.players {
-            for i in 0..REGISTER_COUNT {
-                let mut r = p.registers.get_mut(i).unwrap();
-                r.locked = i >= REGISTER_COUNT + 4 - (p.robot.damage as usize);  // 5 damage -> lock 5; damage -> lock 5,4;
+            let damage = p.robot.damage;
+            for (i, r) in p.registers.iter_mut().enumerate() {
+                r.locked = is_register_locked(i, damage);
             }
         }
         state
     }
     
+    /// The cards in the given register of all active players, in the order they are played: highest priority first.
+    /// Ties (only possible with custom priorities) are broken by card id, then by player id.
     pub fn get_register_cards_sorted_by_priority(&self, register_index: usize) -> Result<Vec<(PlayerID, MoveCard)>, StateError> {
         let mut moves = Vec::with_capacity(self.players.len());
         for p in self.active_players() {
@@ -145,7 +214,7 @@ impl State {
             };
             moves.push(player_card);
         }
-        moves.sort_by(|a, b| a.1.priority.partial_cmp(&b.1.priority).unwrap());
+        moves.sort_by_key(|(player_id, card)| (Reverse(card.priority), card.id, *player_id));
         Ok(moves)
     }
 
@@ -175,4 +244,63 @@ impl State {
     pub fn register_count(&self) -> usize {
         self.players[0].registers.len()
     }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_register_cards_sorted_by_priority() -> Result<(), StateError> {
+        let cases = [
+            // (cards as (id, priority) by player id, expected player order)
+            (vec![(1, 30), (2, 10), (3, 20)], vec![0, 2, 1]),
+            (vec![(3, 10), (1, 10), (2, 10)], vec![1, 2, 0]),      // duplicate priorities: lower card id first
+            (vec![(5, 20), (4, 10), (6, 20)], vec![0, 2, 1]),
+            (vec![(7, 10), (7, 10)], vec![0, 1]),                  // even the same card: lower player id first
+        ];
+        for (cards, expected) in &cases {
+            let players = cards.iter()
+                .enumerate()
+                .map(|(id, (card_id, priority))| {
+                    let robot = RobotBuilder::default().id(id as u32).build().unwrap();
+                    Player::new_with_move(id as u32, robot, MoveCard::new_from_moves(*card_id, *priority, &[]))
+                })
+                .collect();
+            let state = State::new_with_random_deck(Board::default(), players);
+            let order: Vec<PlayerID> = state.get_register_cards_sorted_by_priority(0)?.into_iter()
+                .map(|(player_id, _)| player_id)
+                .collect();
+            assert_eq!(&order, expected, "order for cards {:?}", cards);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_deck_too_small() {
+        let deck_config = |count| ProgramCardDeckConfig {
+            count_1_move: count,
+            count_2_move: 0,
+            count_3_move: 0,
+            count_back_up: 0,
+            count_turn_left: 0,
+            count_turn_right: 0,
+            count_uturn: 0,
+            ..ProgramCardDeckConfig::default()
+        };
+        let config = GameConfig {
+            deck_config: deck_config(17),
+            ..GameConfig::default()
+        };
+        match State::create_from(&config).map_err(|e| e.downcast::<DeckError>()) {
+            Err(Ok(DeckError::NotEnoughCards{ player_count: 2, needed: 18, found: 17 })) => (),
+            other => panic!("Expected NotEnoughCards, got {:?}", other.map(|s| s.deck.cards.len())),
+        }
+
+        let config = GameConfig {
+            deck_config: deck_config(18),
+            ..GameConfig::default()
+        };
+        assert!(State::create_from(&config).is_ok(), "just enough cards");
+    }
 }
\ No newline at end of file
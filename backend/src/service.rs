@@ -1,28 +1,46 @@
-use tonic::{ Request, Response, Status, Code };
+use tonic::{ Request, Response, Status };
+use tonic::codegen::futures_core::Stream;
+use tokio::sync::mpsc;
 use failure::Error;
 
-use std::sync::{ Arc, Mutex };
+use std::pin::Pin;
+use std::sync::{ Arc, Mutex, RwLock };
 
 use crate::protocol::robo_rally_game_server::RoboRallyGame;
-use crate::protocol::{ StartGameRequest, StartGameResponse, GetGameStateRequest, GetGameStateResponse, GameState, SetProgramInputRequest, SetProgramInputResponse, SetStartPositionRequest, SetStartPositionResponse };
+use crate::protocol::{ StartGameRequest, StartGameResponse, GetGameStateRequest, GetGameStateResponse, GameState, SetProgramInputRequest, SetProgramInputResponse, SetStartPositionRequest, SetStartPositionResponse, AdvanceGameRequest, AdvanceGameResponse, ListBoardsRequest, ListBoardsResponse, GetBoardRequest, GetBoardResponse, UploadBoardRequest, UploadBoardResponse, SimulateProgramRequest, SimulateProgramResponse, SimulatedRegister, BoardInfo, Board, WatchGameRequest, WatchGameResponse, SendChatMessageRequest, SendChatMessageResponse, WatchChatRequest, WatchChatResponse, ChatMessage, SeatToken };
 
+use crate::chat::{ Chat, EChatContent };
+use crate::status::into_status;
 use crate::roborally::state as s;
 use crate::roborally::engine::game_engine::{ GameEngine };
 use crate::roborally::engine::player_input::{ ProgramInput, StartPositionInput };
+use crate::roborally::engine::register_engine::ESimulationMode;
+use crate::roborally::engine::program_simulation::simulate_program;
 
-#[derive(Default)]
 pub struct RoboRallyGameService {
     state: Arc<Mutex<s::GameState>>,
+    catalogue: Arc<RwLock<s::BoardCatalogue>>,
+    spectators: Arc<Mutex<Vec<Spectator>>>,
+    chat: Arc<Mutex<Chat>>,
+    chat_watchers: Arc<Mutex<Vec<WatchChatSender>>>,
+}
+
+type WatchGameSender = mpsc::UnboundedSender<Result<WatchGameResponse, Status>>;
+type WatchGameReceiver = mpsc::UnboundedReceiver<Result<WatchGameResponse, Status>>;
+type WatchChatSender = mpsc::UnboundedSender<Result<WatchChatResponse, Status>>;
+type WatchChatReceiver = mpsc::UnboundedReceiver<Result<WatchChatResponse, Status>>;
+
+/// A read-only client, watching the game via WatchGame
+struct Spectator {
+    sender: WatchGameSender,
+    reveal_hands_after_round: bool,
 }
 
 #[tonic::async_trait]
 impl RoboRallyGame for RoboRallyGameService {
-    async fn start_game(&self, _request: Request<StartGameRequest>) -> Result<Response<StartGameResponse>, Status> {
-        let game_state = self.start_new_game().map_err(into_status)?;
-
-        Ok(Response::new(StartGameResponse{
-            state: Some(game_state),
-        }))
+    async fn start_game(&self, request: Request<StartGameRequest>) -> Result<Response<StartGameResponse>, Status> {
+        let response = self.start_new_game(request.into_inner()).map_err(into_status)?;
+        Ok(Response::new(response))
     }
 
     async fn set_start_position(&self, request: Request<SetStartPositionRequest>) -> Result<Response<SetStartPositionResponse>, Status> {
@@ -43,26 +61,170 @@ impl RoboRallyGame for RoboRallyGameService {
         Ok(Response::new(response))
     }
 
+    async fn advance_game(&self, _request: Request<AdvanceGameRequest>) -> Result<Response<AdvanceGameResponse>, Status> {
+        let game_state = self.do_advance_game().map_err(into_status)?;
+
+        let response = AdvanceGameResponse{
+            state: Some(game_state),
+        };
+        Ok(Response::new(response))
+    }
+
     async fn get_game_state(&self, _request: Request<GetGameStateRequest>) -> Result<Response<GetGameStateResponse>, Status> {
         let state = self.state.lock().unwrap();
         let response = GetGameStateResponse {
-            state: Some(GameState::from(&*state)),
+            state: Some(self.to_proto_game_state(&state)),
+        };
+        Ok(Response::new(response))
+    }
+
+    async fn list_boards(&self, _request: Request<ListBoardsRequest>) -> Result<Response<ListBoardsResponse>, Status> {
+        let catalogue = self.catalogue.read().unwrap();
+        let response = ListBoardsResponse {
+            boards: catalogue.entries().map(BoardInfo::from).collect(),
         };
         Ok(Response::new(response))
     }
+
+    async fn get_board(&self, request: Request<GetBoardRequest>) -> Result<Response<GetBoardResponse>, Status> {
+        let response = self.do_get_board(request.into_inner()).map_err(into_status)?;
+        Ok(Response::new(response))
+    }
+
+    async fn upload_board(&self, request: Request<UploadBoardRequest>) -> Result<Response<UploadBoardResponse>, Status> {
+        let response = self.do_upload_board(request.into_inner()).map_err(into_status)?;
+        Ok(Response::new(response))
+    }
+
+    async fn simulate_program(&self, request: Request<SimulateProgramRequest>) -> Result<Response<SimulateProgramResponse>, Status> {
+        let response = self.do_simulate_program(request.into_inner()).map_err(into_status)?;
+        Ok(Response::new(response))
+    }
+
+    type WatchGameStream = Pin<Box<dyn Stream<Item = Result<WatchGameResponse, Status>> + Send + Sync>>;
+
+    async fn watch_game(&self, request: Request<WatchGameRequest>) -> Result<Response<Self::WatchGameStream>, Status> {
+        let mut receiver = self.do_watch_game(request.into_inner());
+        let stream = async_stream::stream! {
+            while let Some(response) = receiver.recv().await {
+                yield response;
+            }
+        };
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn send_chat_message(&self, request: Request<SendChatMessageRequest>) -> Result<Response<SendChatMessageResponse>, Status> {
+        let message = self.do_send_chat_message(request.into_inner())
+            .map_err(into_status)?;
+        Ok(Response::new(SendChatMessageResponse{ message: Some(message) }))
+    }
+
+    type WatchChatStream = Pin<Box<dyn Stream<Item = Result<WatchChatResponse, Status>> + Send + Sync>>;
+
+    async fn watch_chat(&self, _request: Request<WatchChatRequest>) -> Result<Response<Self::WatchChatStream>, Status> {
+        let mut receiver = self.do_watch_chat();
+        let stream = async_stream::stream! {
+            while let Some(response) = receiver.recv().await {
+                yield response;
+            }
+        };
+        Ok(Response::new(Box::pin(stream)))
+    }
 }
 
 impl RoboRallyGameService {
-    fn start_new_game(&self) -> Result<GameState, Error> {
-        let mut game_state = new_game_state()?;
+    pub fn new(catalogue: s::BoardCatalogue) -> Self {
+        RoboRallyGameService {
+            state: Arc::new(Mutex::new(s::GameState::default())),
+            catalogue: Arc::new(RwLock::new(catalogue)),
+            spectators: Arc::new(Mutex::new(vec![])),
+            chat: Arc::new(Mutex::new(Chat::default())),
+            chat_watchers: Arc::new(Mutex::new(vec![])),
+        }
+    }
+
+    /// The game as the players see it
+    fn to_proto_game_state(&self, game_state: &s::GameState) -> GameState {
+        let mut proto_game_state = GameState::from(game_state);
+        proto_game_state.spectator_count = self.spectator_count();
+        proto_game_state
+    }
+
+    fn spectator_count(&self) -> u32 {
+        let mut spectators = self.spectators.lock().unwrap();
+        spectators.retain(|s| !s.sender.is_closed());
+        spectators.len() as u32
+    }
+
+    /// Sends the game to all spectators, dropping those that went away
+    fn notify_spectators(&self, game_state: &s::GameState) {
+        let mut spectators = self.spectators.lock().unwrap();
+        spectators.retain(|s| !s.sender.is_closed());
+        let spectator_count = spectators.len() as u32;
+        spectators.retain(|spectator| {
+            let mut proto_game_state = GameState::from(game_state);
+            proto_game_state.spectator_count = spectator_count;
+            proto_game_state.redact_for_spectators(spectator.reveal_hands_after_round);
+            spectator.sender.send(Ok(WatchGameResponse{ state: Some(proto_game_state) })).is_ok()
+        });
+    }
+
+    fn do_watch_game(&self, request: WatchGameRequest) -> WatchGameReceiver {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let game_state = self.state.lock().unwrap();
+        self.spectators.lock().unwrap().push(Spectator {
+            sender,
+            reveal_hands_after_round: request.reveal_hands_after_round,
+        });
+        // Everyone learns about the new spectator, who starts with the current state
+        self.notify_spectators(&game_state);
+        receiver
+    }
+
+    fn do_send_chat_message(&self, request: SendChatMessageRequest) -> Result<ChatMessage, Error> {
+        let content = EChatContent::parse_from(request.content)?;
+        let game_state = self.state.lock().unwrap();
+        let mut chat = self.chat.lock().unwrap();
+        let message = ChatMessage::from(chat.post(&game_state, request.player_id, &request.seat_token, content)?);
+
+        let mut chat_watchers = self.chat_watchers.lock().unwrap();
+        chat_watchers.retain(|sender| sender.send(Ok(WatchChatResponse{ message: Some(message.clone()), history_cleared: false })).is_ok());
+        Ok(message)
+    }
+
+    /// Watchers get the history of the current game first, then each new message
+    fn do_watch_chat(&self) -> WatchChatReceiver {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let chat = self.chat.lock().unwrap();
+        for message in chat.history() {
+            // Cannot fail, we still hold the receiver
+            let _ = sender.send(Ok(WatchChatResponse{ message: Some(ChatMessage::from(message)), history_cleared: false }));
+        }
+        self.chat_watchers.lock().unwrap().push(sender);
+        receiver
+    }
+
+    fn start_new_game(&self, request: StartGameRequest) -> Result<StartGameResponse, Error> {
+        let config = game_config(request, &self.catalogue.read().unwrap())?;
+        let mut game_state = s::GameState::create_from(&config)?;
         let engine = GameEngine::new();
         engine.initialize(&mut game_state)?;
         
-        let proto_game_state = GameState::from(&game_state);
+        let proto_game_state = self.to_proto_game_state(&game_state);
         let mut persistent_state = self.state.lock().unwrap();
+        self.notify_spectators(&game_state);
         *persistent_state = game_state;
+        // Each game has its own chat
+        let seat_tokens = self.chat.lock().unwrap().start_game(&persistent_state).iter()
+            .map(|(player_id, token)| SeatToken{ player_id: *player_id, token: token.clone() })
+            .collect();
+        let mut chat_watchers = self.chat_watchers.lock().unwrap();
+        chat_watchers.retain(|sender| sender.send(Ok(WatchChatResponse{ message: None, history_cleared: true })).is_ok());
 
-        Ok(proto_game_state)
+        Ok(StartGameResponse {
+            state: Some(proto_game_state),
+            seat_tokens,
+        })
     }
 
     fn do_set_start_position(&self, request: SetStartPositionRequest) -> Result<GameState, Error> {
@@ -74,7 +236,8 @@ impl RoboRallyGameService {
         let engine = GameEngine::new();
         engine.set_start_position(&mut game_state, &start_position_input)?;
 
-        let proto_game_state = GameState::from(&game_state);
+        let proto_game_state = self.to_proto_game_state(&game_state);
+        self.notify_spectators(&game_state);
         *persistent_state = game_state;
 
         Ok(proto_game_state)
@@ -89,21 +252,304 @@ impl RoboRallyGameService {
         let engine = GameEngine::new();
         engine.set_player_program_input(&mut game_state, &program_input)?;
 
-        let proto_game_state = GameState::from(&game_state);
+        let proto_game_state = self.to_proto_game_state(&game_state);
+        self.notify_spectators(&game_state);
+        *persistent_state = game_state;
+
+        Ok(proto_game_state)
+    }
+
+    fn do_advance_game(&self) -> Result<GameState, Error> {
+        let mut persistent_state = self.state.lock().unwrap();
+        let mut game_state = (*persistent_state).clone();
+
+        let engine = GameEngine::new();
+        engine.run_bots(&mut game_state)?;
+
+        let proto_game_state = self.to_proto_game_state(&game_state);
+        self.notify_spectators(&game_state);
         *persistent_state = game_state;
 
         Ok(proto_game_state)
     }
+
+    fn do_simulate_program(&self, request: SimulateProgramRequest) -> Result<SimulateProgramResponse, Error> {
+        let program_input = ProgramInput::parse_from(request.program_input)?;
+        let mode = ESimulationMode::parse_from(request.mode)?;
+
+        // Works on the current round's state, the game itself stays as it is
+        let game_state = self.state.lock().unwrap();
+        let round = game_state.current_round()?;
+        let registers = simulate_program(&round.state, program_input.player_id, &program_input.register_cards_choices, mode)?;
+
+        Ok(SimulateProgramResponse {
+            registers: registers.iter().map(SimulatedRegister::from).collect(),
+        })
+    }
+
+    fn do_get_board(&self, request: GetBoardRequest) -> Result<GetBoardResponse, Error> {
+        let catalogue = self.catalogue.read().unwrap();
+        let entry = catalogue.get_entry_or_fail(&request.name)?;
+        let content = match &entry.board {
+            Some(board) => s::serialize_board(board)?,
+            None => String::new(),
+        };
+
+        Ok(GetBoardResponse {
+            info: Some(BoardInfo::from(entry)),
+            board: entry.board.as_ref().map(|b| Board::from(b.as_ref())),
+            content,
+        })
+    }
+
+    fn do_upload_board(&self, request: UploadBoardRequest) -> Result<UploadBoardResponse, Error> {
+        let mut catalogue = self.catalogue.write().unwrap();
+        match catalogue.upload_board(&request.name, request.content) {
+            Ok(entry) => Ok(UploadBoardResponse {
+                info: Some(BoardInfo::from(entry)),
+                board: entry.board.as_ref().map(|b| Board::from(b.as_ref())),
+            }),
+            // Parser errors are part of the response so the editor can point at them
+            Err(s::BoardCatalogueError::BoardRejected{ name, errors, issues, .. }) => {
+                let entry = s::BoardCatalogueEntry {
+                    name,
+                    board: None,
+                    errors,
+                    issues,
+                };
+                Ok(UploadBoardResponse {
+                    info: Some(BoardInfo::from(&entry)),
+                    board: None,
+                })
+            },
+            Err(err) => Err(err.into()),
+        }
+    }
 }
 
-fn new_game_state() -> Result<s::GameState, Error> {
+fn game_config(request: StartGameRequest, catalogue: &s::BoardCatalogue) -> Result<s::GameConfig, Error> {
     use s::*;
 
-    let config = GameConfig::default();
-    let game_state = GameState::create_from(&config)?;
-    Ok(game_state)
+    let seats = request.seats.into_iter()
+        .map(ESeat::parse_from)
+        .collect::<Result<Vec<ESeat>, _>>()?;
+    let player_config = if seats.is_empty() {
+        PlayerConfig::default()
+    } else {
+        PlayerConfig::with_seats(seats)
+    };
+    let mut deck_config = ProgramCardDeckConfig {
+        priority_scheme: EPriorityScheme::parse_from(request.priority_scheme)?,
+        ..ProgramCardDeckConfig::default()
+    };
+    if !request.deck.is_empty() {
+        deck_config.deck_file = Some(deck_path(&request.deck)?);
+    }
+    let board_name = if request.board.is_empty() { DEFAULT_BOARD } else { &request.board };
+    Ok(GameConfig {
+        deck_config,
+        board_config: BoardConfig::new(catalogue.get_board_or_fail(board_name)?),
+        player_config,
+    })
 }
 
-fn into_status(err: Error) -> Status {
-    Status::new(Code::Internal, format!("{}", err))
+#[cfg(test)]
+mod test {
+    use tonic::{ Code, Request };
+    use failure::Error;
+
+    use crate::protocol::robo_rally_game_server::RoboRallyGame;
+    use crate::protocol::{ GetGameStateRequest, StartGameRequest, WatchGameRequest, SendChatMessageRequest, ChatContent, EEmote, chat_content, SetProgramInputRequest, ProgramInput, SetStartPositionRequest, StartPositionInput, ESeat, UploadBoardRequest, StartGameResponse };
+    use crate::status::error_detail;
+    use crate::roborally::state as s;
+    use super::{ RoboRallyGameService, into_status };
+
+    fn create_service() -> Result<RoboRallyGameService, Error> {
+        let catalogue = s::BoardCatalogue::load_from_directory(std::path::Path::new(s::DEFAULT_BOARDS_DIRECTORY))?;
+        Ok(RoboRallyGameService::new(catalogue))
+    }
+
+    #[test]
+    fn test_spectators() -> Result<(), Error> {
+        let runtime = tokio::runtime::Runtime::new()?;
+        let service = create_service()?;
+        let mut receiver = service.do_watch_game(WatchGameRequest{ reveal_hands_after_round: false });
+
+        let joined = runtime.block_on(receiver.recv()).unwrap().unwrap().state.unwrap();
+        assert_eq!(joined.spectator_count, 1, "spectator count on join");
+
+        let player_state = service.start_new_game(StartGameRequest::default())?.state.unwrap();
+        assert_eq!(player_state.spectator_count, 1, "players see the spectator");
+        let watched = runtime.block_on(receiver.recv()).unwrap().unwrap().state.unwrap();
+        assert_eq!(watched.phase, player_state.phase, "spectators follow the game");
+        assert!(watched.start_state.unwrap().cards.is_empty(), "spectators don't see the deck");
+        assert!(!player_state.start_state.unwrap().cards.is_empty(), "players see the deck");
+
+        drop(receiver);
+        let response = runtime.block_on(service.get_game_state(Request::new(GetGameStateRequest{})))?;
+        assert_eq!(response.into_inner().state.unwrap().spectator_count, 0, "spectator left");
+        Ok(())
+    }
+
+    fn chat_text(text: &str) -> Option<ChatContent> {
+        Some(ChatContent{ content: Some(chat_content::Content::Text(String::from(text))) })
+    }
+
+    /// The token of player_id's seat, as returned by StartGame
+    fn seat_token(response: &StartGameResponse, player_id: u32) -> String {
+        response.seat_tokens.iter().find(|t| t.player_id == player_id).unwrap().token.clone()
+    }
+
+    #[test]
+    fn test_chat() -> Result<(), Error> {
+        let runtime = tokio::runtime::Runtime::new()?;
+        let service = create_service()?;
+        let response = service.start_new_game(StartGameRequest::default())?;
+        assert_eq!(response.seat_tokens.len(), 2, "a token per human seat");
+        let token = seat_token(&response, 0);
+        let message = |player_id, content| SendChatMessageRequest{ player_id, content, seat_token: token.clone() };
+
+        let sent = service.do_send_chat_message(message(0, chat_text(" hello ")))?;
+        assert_eq!(sent.content, chat_text("hello"), "filtered text");
+        assert!(service.do_send_chat_message(message(0, chat_text(""))).is_err(), "empty message");
+        assert!(service.do_send_chat_message(message(0, None)).is_err(), "no content");
+        assert!(service.do_send_chat_message(message(42, chat_text("hi"))).is_err(), "unknown sender");
+        assert!(service.do_send_chat_message(message(1, chat_text("hi"))).is_err(), "token of another seat");
+
+        let mut receiver = service.do_watch_chat();
+        let history = runtime.block_on(receiver.recv()).unwrap().unwrap().message.unwrap();
+        assert_eq!(history, sent, "history first");
+        let emote = ChatContent{ content: Some(chat_content::Content::Emote(EEmote::Boom.into())) };
+        service.do_send_chat_message(message(0, Some(emote.clone())))?;
+        let new = runtime.block_on(receiver.recv()).unwrap().unwrap().message.unwrap();
+        assert_eq!(new.content, Some(emote), "then new messages");
+
+        let response = service.start_new_game(StartGameRequest::default())?;
+        let cleared = runtime.block_on(receiver.recv()).unwrap().unwrap();
+        assert!(cleared.history_cleared && cleared.message.is_none(), "watchers learn about the new game");
+        let mut receiver = service.do_watch_chat();
+        let request = SendChatMessageRequest{ player_id: 0, content: chat_text("rematch"), seat_token: seat_token(&response, 0) };
+        let sent = service.do_send_chat_message(request)?;
+        let first = runtime.block_on(receiver.recv()).unwrap().unwrap().message.unwrap();
+        assert_eq!(first, sent, "new game, fresh history");
+        Ok(())
+    }
+
+    #[test]
+    fn test_service_errors() {
+        let service = RoboRallyGameService::new(s::BoardCatalogue::default());
+        let program_input = ProgramInput{ player_id: 0, register_cards_choices: vec![] };
+        let err = service.do_set_program_input(SetProgramInputRequest{ program_input: Some(program_input) }).unwrap_err();
+        let status = into_status(err);
+        let (code, detail) = (status.code(), error_detail(&status).unwrap());
+        assert_eq!(code, Code::FailedPrecondition, "game not started");
+        assert_eq!(detail.reason, "INVALID_GAME_PHASE");
+
+        let text = Some(ChatContent{ content: Some(chat_content::Content::Text(String::from("hi"))) });
+        let err = service.do_send_chat_message(SendChatMessageRequest{ player_id: 7, content: text, seat_token: String::new() }).unwrap_err();
+        let status = into_status(err);
+        let (code, detail) = (status.code(), error_detail(&status).unwrap());
+        assert_eq!(code, Code::PermissionDenied, "not seated");
+        assert_eq!((detail.domain.as_str(), detail.reason.as_str()), ("chat", "UNKNOWN_SENDER"));
+    }
+
+    #[test]
+    fn test_chat_with_wrong_seat_token() -> Result<(), Error> {
+        let service = create_service()?;
+        service.start_new_game(StartGameRequest::default())?;
+
+        let request = SendChatMessageRequest{ player_id: 0, content: chat_text("hi"), seat_token: String::from("guessed") };
+        let status = into_status(service.do_send_chat_message(request).unwrap_err());
+        let (code, detail) = (status.code(), error_detail(&status).unwrap());
+        assert_eq!(code, Code::PermissionDenied, "wrong token");
+        assert_eq!((detail.domain.as_str(), detail.reason.as_str()), ("chat", "INVALID_SEAT_TOKEN"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_card_not_in_hand() -> Result<(), Error> {
+        let service = create_service()?;
+        service.start_new_game(StartGameRequest{ seats: vec![ESeat::Human.into()], ..StartGameRequest::default() })?;
+        let start_position = StartPositionInput{ player_id: 0, start_position_id: 1 };
+        service.do_set_start_position(SetStartPositionRequest{ start_position: Some(start_position) })?;
+
+        let program_input = ProgramInput{ player_id: 0, register_cards_choices: vec![9999; s::REGISTER_COUNT] };
+        let err = service.do_set_program_input(SetProgramInputRequest{ program_input: Some(program_input) }).unwrap_err();
+        let status = into_status(err);
+        let detail = error_detail(&status).unwrap();
+        assert_eq!(status.code(), Code::InvalidArgument, "{}", status.message());
+        assert_eq!((detail.domain.as_str(), detail.reason.as_str()), ("state", "INVALID_PROGRAM_CARD_CHOICE"));
+        assert_eq!(detail.metadata.get("move_card_id").map(String::as_str), Some("9999"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_start_game_with_deck() -> Result<(), Error> {
+        let service = create_service()?;
+        let request = StartGameRequest{ deck: String::from("variants"), ..StartGameRequest::default() };
+        service.start_new_game(request)?;
+        let deck = &service.state.lock().unwrap().start_state.deck;
+        assert!(deck.cards.iter().any(|c| c.kind == s::ECardKind::StepLeft), "variant cards in the deck");
+
+        let cases = [
+            (StartGameRequest{ deck: String::from("../decks/variants"), ..StartGameRequest::default() }, Code::InvalidArgument, "INVALID_DECK_NAME"),
+            (StartGameRequest{ deck: String::from("no-such-deck"), ..StartGameRequest::default() }, Code::NotFound, "UNKNOWN_DECK"),
+            (StartGameRequest{ priority_scheme: 7, ..StartGameRequest::default() }, Code::InvalidArgument, "INVALID_PRIORITY_SCHEME"),
+        ];
+        for (request, expected_code, expected_reason) in cases.iter().cloned() {
+            let status = into_status(service.start_new_game(request).unwrap_err());
+            assert_eq!(status.code(), expected_code, "{}", status.message());
+            assert_eq!(error_detail(&status).unwrap().reason, expected_reason);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_advance_bots_only_game() -> Result<(), Error> {
+        let service = create_service()?;
+        let seats = vec![ESeat::BotRandom.into(), ESeat::BotRandom.into()];
+        service.start_new_game(StartGameRequest{ seats, ..StartGameRequest::default() })?;
+
+        let mut round_count = service.state.lock().unwrap().all_rounds().count();
+        for _ in 0..3 {
+            let game_state = service.do_advance_game()?;
+            if game_state.game_result.is_some() {
+                return Ok(());
+            }
+            assert_eq!(game_state.rounds.len(), round_count + 1, "one more round played");
+            round_count = game_state.rounds.len();
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_board_from_catalogue() -> Result<(), Error> {
+        // Not the board of that name in the default directory
+        let content = std::fs::read_to_string("./data/boards/test-rotator.brd")?;
+        let mut catalogue = s::BoardCatalogue::default();
+        let board = catalogue.upload_board(s::DEFAULT_BOARD, content)?.board.clone().unwrap();
+        let service = RoboRallyGameService::new(catalogue);
+
+        service.start_new_game(StartGameRequest::default())?;
+        assert_eq!(service.state.lock().unwrap().start_state.board, board, "board of the catalogue");
+        Ok(())
+    }
+
+    #[test]
+    fn test_start_game_on_uploaded_board() -> Result<(), Error> {
+        let service = create_service()?;
+        let content = std::fs::read_to_string("./data/boards/test-rotator.brd")?;
+        service.do_upload_board(UploadBoardRequest{ name: String::from("my-board"), content })?;
+
+        let request = StartGameRequest{ board: String::from("my-board"), ..StartGameRequest::default() };
+        let game_state = service.start_new_game(request)?.state.unwrap();
+        let board = game_state.start_state.unwrap().board.unwrap();
+        assert_eq!((board.size_x, board.size_y), (3, 3), "size of the uploaded board");
+
+        let request = StartGameRequest{ board: String::from("no-such-board"), ..StartGameRequest::default() };
+        let status = into_status(service.start_new_game(request).unwrap_err());
+        assert_eq!(status.code(), Code::NotFound, "{}", status.message());
+        assert_eq!(error_detail(&status).unwrap().reason, "BOARD_NOT_FOUND");
+        Ok(())
+    }
 }
\ No newline at end of file
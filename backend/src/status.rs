@@ -0,0 +1,271 @@
+//! Maps errors to gRPC status codes. Each status carries an ErrorDetail in its details, so clients can tell e.g. a
+//! move in the wrong phase (FAILED_PRECONDITION) or a card that is not in hand (INVALID_ARGUMENT) apart from bugs on
+//! our side (INTERNAL).
+use bytes::Bytes;
+use failure::{ Error, Fail };
+use prost::Message;
+use tonic::{ Code, Status };
+
+use std::fmt::Display;
+
+use crate::chat::ChatError;
+use crate::protocol::{ ErrorDetail, ProtocolError };
+use crate::roborally::state::{ BoardCatalogueError, DeckError, ParserError, SerializerError, SourceLocation, StateError };
+use crate::roborally::engine::game_engine::EngineError;
+use crate::roborally::engine::register_engine::RegisterEngineError;
+use crate::roborally::engine::program_simulation::SimulationError;
+use crate::roborally::engine::bot::BotError;
+
+/// The gRPC code of an error and what we tell the client about it
+#[derive(Debug)]
+struct ErrorStatus {
+    code: Code,
+    detail: ErrorDetail,
+}
+
+impl ErrorStatus {
+    fn new(code: Code, domain: &str, reason: &str) -> ErrorStatus {
+        ErrorStatus {
+            code,
+            detail: ErrorDetail {
+                domain: String::from(domain),
+                reason: String::from(reason),
+                ..ErrorDetail::default()
+            },
+        }
+    }
+
+    fn with(mut self, key: &str, value: impl Display) -> ErrorStatus {
+        self.detail.metadata.insert(String::from(key), format!("{}", value));
+        self
+    }
+}
+
+pub fn into_status(err: Error) -> Status {
+    let ErrorStatus{ code, detail } = err.iter_chain()
+        .find_map(error_status)
+        .unwrap_or_else(|| ErrorStatus::new(Code::Internal, "server", "INTERNAL"));
+    let mut details = Vec::new();
+    // Cannot fail, a Vec grows as needed
+    detail.encode(&mut details).unwrap();
+    Status::with_details(code, format!("{}", err), Bytes::from(details))
+}
+
+/// The ErrorDetail of a status created by into_status
+pub fn error_detail(status: &Status) -> Option<ErrorDetail> {
+    ErrorDetail::decode(status.details()).ok()
+}
+
+/// None for errors we know nothing about. into_status then looks at their cause.
+fn error_status(fail: &dyn Fail) -> Option<ErrorStatus> {
+    if let Some(err) = fail.downcast_ref::<StateError>() {
+        Some(state_error_status(err))
+    } else if let Some(err) = fail.downcast_ref::<EngineError>() {
+        Some(engine_error_status(err))
+    } else if let Some(err) = fail.downcast_ref::<RegisterEngineError>() {
+        Some(register_engine_error_status(err))
+    } else if let Some(err) = fail.downcast_ref::<SimulationError>() {
+        Some(simulation_error_status(err))
+    } else if let Some(err) = fail.downcast_ref::<BotError>() {
+        Some(bot_error_status(err))
+    } else if let Some(err) = fail.downcast_ref::<ParserError>() {
+        Some(parser_error_status(err))
+    } else if let Some(err) = fail.downcast_ref::<BoardCatalogueError>() {
+        Some(board_catalogue_error_status(err))
+    } else if let Some(err) = fail.downcast_ref::<SerializerError>() {
+        Some(serializer_error_status(err))
+    } else if let Some(err) = fail.downcast_ref::<DeckError>() {
+        Some(deck_error_status(err))
+    } else if let Some(err) = fail.downcast_ref::<ChatError>() {
+        Some(chat_error_status(err))
+    } else {
+        fail.downcast_ref::<ProtocolError>().map(protocol_error_status)
+    }
+}
+
+fn state_error_status(err: &StateError) -> ErrorStatus {
+    use StateError::*;
+    let status = |code, reason| ErrorStatus::new(code, "state", reason);
+    match err {
+        RobotNotFoundID{ robot_id } => status(Code::NotFound, "ROBOT_NOT_FOUND").with("robot_id", robot_id),
+        RobotNotFoundPlayerID{ player_id } => status(Code::NotFound, "ROBOT_NOT_FOUND").with("player_id", player_id),
+        PlayerNotFound{ player_id } => status(Code::NotFound, "PLAYER_NOT_FOUND").with("player_id", player_id),
+        EmptyProgramRegister{ player_id } => status(Code::Internal, "EMPTY_PROGRAM_REGISTER").with("player_id", player_id),
+        // No game started yet
+        GameStateMissingRound{ .. } => status(Code::FailedPrecondition, "NO_ROUND"),
+        RoundNotFound{ round_id } => status(Code::NotFound, "ROUND_NOT_FOUND").with("round_id", round_id),
+        DoublePlayerInput{ player_id } => status(Code::FailedPrecondition, "DOUBLE_PLAYER_INPUT").with("player_id", player_id),
+        InvalidProgramCardChoice{ player_id, move_card_id, .. } => status(Code::InvalidArgument, "INVALID_PROGRAM_CARD_CHOICE")
+            .with("player_id", player_id)
+            .with("move_card_id", move_card_id),
+        PositionOffBoard{ .. } => status(Code::Internal, "POSITION_OFF_BOARD"),
+        StartPositionNotFoundID{ start_position_id } => status(Code::InvalidArgument, "START_POSITION_NOT_FOUND")
+            .with("start_position_id", start_position_id),
+        InvalidBoard{ issues } => status(Code::FailedPrecondition, "INVALID_BOARD").with("issue_count", issues.len()),
+    }
+}
+
+fn engine_error_status(err: &EngineError) -> ErrorStatus {
+    use EngineError::*;
+    let status = |code, reason| ErrorStatus::new(code, "engine", reason);
+    match err {
+        InvalidPlayerInput{ player_id, .. } => status(Code::InvalidArgument, "INVALID_PLAYER_INPUT").with("player_id", player_id),
+        InvalidStartPositionInput{ player_id, start_position_id } => status(Code::InvalidArgument, "INVALID_START_POSITION_INPUT")
+            .with("player_id", player_id)
+            .with("start_position_id", start_position_id),
+        InvalidRoundPhase{ expected, actual } => status(Code::FailedPrecondition, "INVALID_ROUND_PHASE")
+            .with("expected", format!("{:?}", expected))
+            .with("actual", format!("{:?}", actual)),
+        InvalidGamePhase{ expected, actual } => status(Code::FailedPrecondition, "INVALID_GAME_PHASE")
+            .with("expected", format!("{:?}", expected))
+            .with("actual", format!("{:?}", actual)),
+        StateError{ err } => state_error_status(err),
+        RegisterEngineError{ err } => register_engine_error_status(err),
+        BotError{ err } => bot_error_status(err),
+    }
+}
+
+fn register_engine_error_status(err: &RegisterEngineError) -> ErrorStatus {
+    match err {
+        RegisterEngineError::GenericAlgorithmError{ .. } => ErrorStatus::new(Code::Internal, "register_engine", "ALGORITHM_ERROR"),
+        RegisterEngineError::StateError{ err } => state_error_status(err),
+    }
+}
+
+fn simulation_error_status(err: &SimulationError) -> ErrorStatus {
+    match err {
+        SimulationError::InvalidProgram{ player_id, .. } => ErrorStatus::new(Code::InvalidArgument, "simulation", "INVALID_PROGRAM")
+            .with("player_id", player_id),
+        SimulationError::RegisterEngineError{ err } => register_engine_error_status(err),
+        SimulationError::StateError{ err } => state_error_status(err),
+    }
+}
+
+fn bot_error_status(err: &BotError) -> ErrorStatus {
+    match err {
+        BotError::NoInputFound{ player_id, .. } => ErrorStatus::new(Code::Internal, "bot", "NO_INPUT_FOUND")
+            .with("player_id", player_id),
+        BotError::SimulationError{ err } => register_engine_error_status(err),
+        BotError::StateError{ err } => state_error_status(err),
+    }
+}
+
+fn parser_error_status(err: &ParserError) -> ErrorStatus {
+    use ParserError::*;
+    let status = |reason, location: &SourceLocation| ErrorStatus::new(Code::InvalidArgument, "board_parser", reason)
+        .with("line", location.line)
+        .with("column", location.column);
+    match err {
+        FileError{ .. } => ErrorStatus::new(Code::Internal, "board_parser", "FILE_ERROR"),
+        WallNotFound{ location, .. } => status("WALL_NOT_FOUND", location),
+        HorizontalWallNotFound{ location, .. } => status("HORIZONTAL_WALL_NOT_FOUND", location),
+        UnknownTileType{ location, .. } => status("UNKNOWN_TILE_TYPE", location),
+        EndOfRow{ location } => status("END_OF_ROW", location),
+        UnexpectedRowLength{ location, .. } => status("UNEXPECTED_ROW_LENGTH", location),
+        MissingTileForHWall{ location, .. } => status("MISSING_TILE_FOR_HWALL", location),
+        DuplicateStartPositionId{ id, location, .. } => status("DUPLICATE_START_POSITION_ID", location).with("start_position_id", id),
+    }
+}
+
+fn board_catalogue_error_status(err: &BoardCatalogueError) -> ErrorStatus {
+    use BoardCatalogueError::*;
+    let status = |code, reason| ErrorStatus::new(code, "board_catalogue", reason);
+    match err {
+        DirectoryError{ .. } => status(Code::Internal, "DIRECTORY_ERROR"),
+        BoardNotFound{ name } => status(Code::NotFound, "BOARD_NOT_FOUND").with("name", name),
+        BoardNotParsed{ name } => status(Code::FailedPrecondition, "BOARD_NOT_PARSED").with("name", name),
+        InvalidBoardName{ name } => status(Code::InvalidArgument, "INVALID_BOARD_NAME").with("name", name),
+        BoardRejected{ name, .. } => status(Code::InvalidArgument, "BOARD_REJECTED").with("name", name),
+    }
+}
+
+fn serializer_error_status(err: &SerializerError) -> ErrorStatus {
+    use SerializerError::*;
+    let reason = match err {
+        UnsupportedTileType{ .. } => "UNSUPPORTED_TILE_TYPE",
+        UnsupportedStartPosition{ .. } => "UNSUPPORTED_START_POSITION",
+        InconsistentSize{ .. } => "INCONSISTENT_SIZE",
+    };
+    // Boards in the catalogue are valid, so they should always be writable
+    ErrorStatus::new(Code::Internal, "board_serializer", reason)
+}
+
+fn deck_error_status(err: &DeckError) -> ErrorStatus {
+    use DeckError::*;
+    // Decks are configured on the server
+    let status = |reason| ErrorStatus::new(Code::Internal, "deck", reason);
+    match err {
+        FileError{ .. } => status("FILE_ERROR"),
+        SyntaxError{ line, .. } => status("SYNTAX_ERROR").with("line", line),
+        NotEnoughPriorities{ card, .. } => status("NOT_ENOUGH_PRIORITIES").with("card", card),
+        EmptyDeck => status("EMPTY_DECK"),
+        // Named by the client
+        InvalidDeckName{ name } => ErrorStatus::new(Code::InvalidArgument, "deck", "INVALID_DECK_NAME").with("name", name),
+        UnknownDeck{ name } => ErrorStatus::new(Code::NotFound, "deck", "UNKNOWN_DECK").with("name", name),
+        // Depends on the number of seats the client asked for
+        NotEnoughCards{ player_count, needed, found } => ErrorStatus::new(Code::FailedPrecondition, "deck", "NOT_ENOUGH_CARDS")
+            .with("player_count", player_count)
+            .with("needed", needed)
+            .with("found", found),
+    }
+}
+
+fn chat_error_status(err: &ChatError) -> ErrorStatus {
+    use ChatError::*;
+    let status = |code, reason| ErrorStatus::new(code, "chat", reason);
+    match err {
+        UnknownSender{ player_id } => status(Code::PermissionDenied, "UNKNOWN_SENDER").with("player_id", player_id),
+        BotSender{ player_id } => status(Code::PermissionDenied, "BOT_SENDER").with("player_id", player_id),
+        InvalidSeatToken{ player_id } => status(Code::PermissionDenied, "INVALID_SEAT_TOKEN").with("player_id", player_id),
+        EmptyMessage => status(Code::InvalidArgument, "EMPTY_MESSAGE"),
+        MessageTooLong{ length, max_length } => status(Code::InvalidArgument, "MESSAGE_TOO_LONG")
+            .with("length", length)
+            .with("max_length", max_length),
+        MessageRejected{ .. } => status(Code::InvalidArgument, "MESSAGE_REJECTED"),
+    }
+}
+
+fn protocol_error_status(err: &ProtocolError) -> ErrorStatus {
+    use ProtocolError::*;
+    let status = |reason| ErrorStatus::new(Code::InvalidArgument, "protocol", reason);
+    match err {
+        MissingPlayerInput{} => status("MISSING_PLAYER_INPUT"),
+        InvalidSeat{ seat } => status("INVALID_SEAT").with("seat", seat),
+        InvalidPriorityScheme{ scheme } => status("INVALID_PRIORITY_SCHEME").with("scheme", scheme),
+        InvalidSimulationMode{ mode } => status("INVALID_SIMULATION_MODE").with("mode", mode),
+        MissingChatContent{} => status("MISSING_CHAT_CONTENT"),
+        InvalidEmote{ emote } => status("INVALID_EMOTE").with("emote", emote),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use failure::{ Error, format_err };
+    use tonic::Code;
+
+    use crate::protocol::ErrorDetail;
+    use crate::roborally::state::StateError;
+    use super::{ into_status, error_detail };
+
+    pub fn code_and_detail(err: Error) -> (Code, ErrorDetail) {
+        let status = into_status(err);
+        (status.code(), error_detail(&status).expect("error detail"))
+    }
+
+    #[test]
+    fn test_state_error() {
+        let err = StateError::InvalidProgramCardChoice{ player_id: 1, move_card_id: 42, hand: String::new() };
+        let (code, detail) = code_and_detail(err.into());
+        assert_eq!(code, Code::InvalidArgument);
+        assert_eq!((detail.domain.as_str(), detail.reason.as_str()), ("state", "INVALID_PROGRAM_CARD_CHOICE"));
+        assert_eq!(detail.metadata.get("move_card_id").map(String::as_str), Some("42"));
+    }
+
+    #[test]
+    fn test_unknown_error() {
+        let (code, detail) = code_and_detail(format_err!("something broke"));
+        assert_eq!(code, Code::Internal);
+        assert_eq!((detail.domain.as_str(), detail.reason.as_str()), ("server", "INTERNAL"));
+    }
+
+}
\ No newline at end of file